@@ -1,9 +1,28 @@
-use crate::types::{AnalysisResults, CsvChunk, SensorAggregation};
+use crate::types::{AnalysisResults, CsvChunk, MetricSpec, SensorAggregation};
 use anyhow::{Context, Result};
 use chrono::NaiveDate;
+use csv::ReaderBuilder;
 use polars::prelude::*;
+use serde::Deserialize;
 use std::time::Instant;
 
+/// A single sensor reading row. Unknown header columns are reported and
+/// dropped rather than silently matched by content heuristics.
+#[derive(Debug, Deserialize)]
+struct SensorRow {
+    id: Option<String>,
+    device: Option<String>,
+    data: Option<String>,
+    latitude: Option<String>,
+    longitude: Option<String>,
+    temperatura: Option<f64>,
+    umidade: Option<f64>,
+    luminosidade: Option<f64>,
+    ruido: Option<f64>,
+    eco2: Option<f64>,
+    etvoc: Option<f64>,
+}
+
 /// Analyze a CSV chunk using Polars (pure Rust replacement for Python script)
 pub fn analyze_csv_chunk(chunk: &CsvChunk) -> Result<AnalysisResults> {
     let start_time = Instant::now();
@@ -19,73 +38,94 @@ pub fn analyze_csv_chunk(chunk: &CsvChunk) -> Result<AnalysisResults> {
     // Create CSV content with header
     let csv_content = format!("{}\n{}", chunk.header, chunk.data);
 
-    // Parse CSV manually since Polars scan_csv is not available for in-memory data
-    let lines: Vec<&str> = csv_content.lines().collect();
+    // Parse with the `csv` crate so quoted fields, embedded delimiters, and
+    // escaped quotes are handled correctly instead of a naive `split('|')`.
+    let mut reader = ReaderBuilder::new()
+        .delimiter(b'|')
+        .has_headers(true)
+        .flexible(false)
+        .from_reader(csv_content.as_bytes());
+
+    let header_columns: Vec<String> = reader
+        .headers()
+        .with_context(|| "Failed to read CSV header")?
+        .iter()
+        .map(|col| col.trim().to_string())
+        .collect();
 
-    if lines.is_empty() {
-        return Ok(AnalysisResults {
-            aggregations: Vec::new(),
-            total_lines_processed: 0,
-            processing_time_ms: start_time.elapsed().as_millis() as f64,
-        });
+    let mut ids: Vec<Option<String>> = Vec::new();
+    let mut devices: Vec<Option<String>> = Vec::new();
+    let mut datas: Vec<Option<String>> = Vec::new();
+    let mut latitudes: Vec<Option<String>> = Vec::new();
+    let mut longitudes: Vec<Option<String>> = Vec::new();
+    let mut temperaturas: Vec<Option<f64>> = Vec::new();
+    let mut umidades: Vec<Option<f64>> = Vec::new();
+    let mut luminosidades: Vec<Option<f64>> = Vec::new();
+    let mut ruidos: Vec<Option<f64>> = Vec::new();
+    let mut eco2s: Vec<Option<f64>> = Vec::new();
+    let mut etvocs: Vec<Option<f64>> = Vec::new();
+
+    let mut malformed_rows = 0usize;
+
+    for result in reader.deserialize::<SensorRow>() {
+        match result {
+            Ok(row) => {
+                ids.push(row.id);
+                devices.push(row.device);
+                datas.push(row.data);
+                latitudes.push(row.latitude);
+                longitudes.push(row.longitude);
+                temperaturas.push(row.temperatura);
+                umidades.push(row.umidade);
+                luminosidades.push(row.luminosidade);
+                ruidos.push(row.ruido);
+                eco2s.push(row.eco2);
+                etvocs.push(row.etvoc);
+            }
+            Err(err) => {
+                malformed_rows += 1;
+                eprintln!("Warning: Skipping malformed CSV row: {}", err);
+            }
+        }
     }
 
-    // Get header and data lines
-    let header_line = lines[0];
-    let data_lines = &lines[1..];
+    if malformed_rows > 0 {
+        println!(
+            "[PARSE] Skipped {} malformed row(s) while parsing chunk",
+            malformed_rows
+        );
+    }
 
-    if data_lines.is_empty() {
+    if devices.is_empty() {
         return Ok(AnalysisResults {
             aggregations: Vec::new(),
-            total_lines_processed: 0,
+            total_lines_processed: chunk.line_count,
             processing_time_ms: start_time.elapsed().as_millis() as f64,
         });
     }
 
-    // Parse header to get column names
-    let column_names: Vec<&str> = header_line.split('|').collect();
-
-    // Parse data rows, skipping malformed lines and lines that look like JSON or CSV artifacts
-    let mut columns: Vec<Vec<AnyValue>> = vec![Vec::new(); column_names.len()];
-
-    for line in data_lines {
-        let values: Vec<&str> = line.split('|').collect();
-        if values.len() != column_names.len() {
-            // Skip malformed lines
-            continue;
-        }
-        // Additional filter: skip lines that look like JSON (start with '{' or '[')
-        if let Some(first) = values[0].chars().next() {
-            if first == '{' || first == '[' {
+    // Build a Series only for columns actually present in the header, in the
+    // header's own order.
+    let mut series_vec = Vec::new();
+    for col_name in &header_columns {
+        let series = match col_name.as_str() {
+            "id" => Series::new("id", &ids),
+            "device" => Series::new("device", &devices),
+            "data" => Series::new("data", &datas),
+            "latitude" => Series::new("latitude", &latitudes),
+            "longitude" => Series::new("longitude", &longitudes),
+            "temperatura" => Series::new("temperatura", &temperaturas),
+            "umidade" => Series::new("umidade", &umidades),
+            "luminosidade" => Series::new("luminosidade", &luminosidades),
+            "ruido" => Series::new("ruido", &ruidos),
+            "eco2" => Series::new("eco2", &eco2s),
+            "etvoc" => Series::new("etvoc", &etvocs),
+            other => {
+                println!("Warning: Unrecognized column '{}' skipped", other);
                 continue;
             }
-        }
-        // Extra filter: skip lines where all columns are numeric or empty (likely artifact rows)
-        if values
-            .iter()
-            .all(|v| v.trim().is_empty() || v.trim().parse::<f64>().is_ok())
-        {
-            continue;
-        }
-        // Extra filter: skip lines where any column contains 'device_id', 'device_name', or 'variable' (likely JSON keys)
-        if values
-            .iter()
-            .any(|v| v.contains("device_id") || v.contains("device_name") || v.contains("variable"))
-        {
-            continue;
-        }
-        for (i, &value) in values.iter().enumerate() {
-            columns[i].push(AnyValue::String(value));
-        }
-    }
-
-    // Create Series for each column
-    let mut series_vec = Vec::new();
-    for (i, &col_name) in column_names.iter().enumerate() {
-        if i < columns.len() {
-            let series = Series::new(col_name, &columns[i]);
-            series_vec.push(series);
-        }
+        };
+        series_vec.push(series);
     }
 
     // Create DataFrame
@@ -93,10 +133,10 @@ pub fn analyze_csv_chunk(chunk: &CsvChunk) -> Result<AnalysisResults> {
         DataFrame::new(series_vec).with_context(|| "Failed to create DataFrame from parsed CSV")?;
 
     // Apply the same transformations as the Python script
-    let processed_df = process_dataframe(df)?;
+    let processed_df = process_dataframe(df, &chunk.metrics)?;
 
     // Convert to our result format
-    let aggregations = dataframe_to_aggregations(processed_df)?;
+    let aggregations = dataframe_to_aggregations(processed_df, &chunk.metrics)?;
 
     let processing_time = start_time.elapsed().as_millis() as f64;
 
@@ -107,8 +147,23 @@ pub fn analyze_csv_chunk(chunk: &CsvChunk) -> Result<AnalysisResults> {
     })
 }
 
+/// Build the Polars expression for a single requested metric on a sensor column
+fn metric_expr(sensor: &str, metric: &MetricSpec) -> Expr {
+    let column_name = metric.column_name();
+    match metric {
+        MetricSpec::Max => col(sensor).max().alias(&column_name),
+        MetricSpec::Mean => col(sensor).mean().alias(&column_name),
+        MetricSpec::Min => col(sensor).min().alias(&column_name),
+        MetricSpec::Std => col(sensor).std(1).alias(&column_name),
+        MetricSpec::Median => col(sensor).median().alias(&column_name),
+        MetricSpec::Quantile(p) => col(sensor)
+            .quantile(lit(*p as f64 / 100.0), QuantileInterpolation::Linear)
+            .alias(&column_name),
+    }
+}
+
 /// Process dataframe with the same logic as the Python script
-fn process_dataframe(mut df: DataFrame) -> Result<DataFrame> {
+fn process_dataframe(mut df: DataFrame, metrics: &[MetricSpec]) -> Result<DataFrame> {
     // Drop unnecessary columns if they exist
     let columns_to_drop = ["id", "latitude", "longitude"];
     let existing_columns: Vec<String> = df
@@ -134,7 +189,7 @@ fn process_dataframe(mut df: DataFrame) -> Result<DataFrame> {
     // Check if device column exists
     if !df.get_column_names().contains(&"device") {
         println!("Warning: DataFrame does not contain 'device' column");
-        return create_empty_result_dataframe();
+        return create_empty_result_dataframe(metrics);
     }
 
     // Convert to LazyFrame for better API support
@@ -150,7 +205,7 @@ fn process_dataframe(mut df: DataFrame) -> Result<DataFrame> {
 
     if df.height() == 0 {
         println!("Warning: DataFrame is empty after normalization");
-        return create_empty_result_dataframe();
+        return create_empty_result_dataframe(metrics);
     }
 
     // Convert back to LazyFrame for transformations
@@ -206,7 +261,7 @@ fn process_dataframe(mut df: DataFrame) -> Result<DataFrame> {
 
     if sensor_columns.is_empty() {
         println!("Warning: No sensor columns found");
-        return create_empty_result_dataframe();
+        return create_empty_result_dataframe(metrics);
     }
 
     // Cast sensor columns to Float64 (only if not already Float64, matching Python script)
@@ -232,38 +287,47 @@ fn process_dataframe(mut df: DataFrame) -> Result<DataFrame> {
         lf.schema()?.iter_fields().map(|f| col(f.name())).collect();
     lf = lf.drop_nulls(Some(all_columns_after_cast));
 
+    // Dictionary-encode the group-by keys: a few thousand distinct device IDs
+    // repeated across millions of rows are far cheaper to group on as u32
+    // indices into a shared string pool than as full String comparisons.
+    lf = lf.with_columns([
+        col("device").cast(DataType::Categorical(None, CategoricalOrdering::Physical)),
+        col("ano-mes").cast(DataType::Categorical(None, CategoricalOrdering::Physical)),
+    ]);
+
     // Process each sensor and create aggregations
     let mut result_dfs = Vec::new();
 
     for &sensor in &sensor_columns {
+        let agg_exprs: Vec<Expr> = metrics.iter().map(|m| metric_expr(sensor, m)).collect();
         let sensor_lf = lf
             .clone()
             .group_by([col("device"), col("ano-mes")])
-            .agg([
-                col(sensor).max().alias("valor_maximo"),
-                col(sensor).mean().alias("valor_medio"),
-                col(sensor).min().alias("valor_minimo"),
-            ])
-            .with_columns([lit(sensor).alias("sensor")]);
+            .agg(agg_exprs)
+            .with_columns([lit(sensor)
+                .cast(DataType::Categorical(None, CategoricalOrdering::Physical))
+                .alias("sensor")]);
 
         result_dfs.push(sensor_lf);
     }
 
     // Concatenate all sensor results
+    let mut select_exprs = vec![
+        // Decode back to String only here, at the very end, so
+        // `dataframe_to_aggregations` keeps treating these as plain strings.
+        col("device").cast(DataType::String),
+        col("ano-mes").cast(DataType::String),
+        col("sensor").cast(DataType::String),
+    ];
+    select_exprs.extend(metrics.iter().map(|m| col(&m.column_name())));
+
     let final_result = concat(result_dfs, UnionArgs::default())
         .with_context(|| "Failed to concatenate sensor results")?
         .sort(
             ["device", "ano-mes", "sensor"],
             SortMultipleOptions::default(),
         )
-        .select([
-            col("device"),
-            col("ano-mes"),
-            col("sensor"),
-            col("valor_maximo"),
-            col("valor_medio"),
-            col("valor_minimo"),
-        ]);
+        .select(select_exprs);
 
     final_result
         .collect()
@@ -271,21 +335,26 @@ fn process_dataframe(mut df: DataFrame) -> Result<DataFrame> {
 }
 
 /// Create an empty result dataframe with the correct schema
-fn create_empty_result_dataframe() -> Result<DataFrame> {
-    let schema = Schema::from_iter(vec![
+fn create_empty_result_dataframe(metrics: &[MetricSpec]) -> Result<DataFrame> {
+    let mut fields = vec![
         Field::new("device", DataType::String),
         Field::new("ano-mes", DataType::String),
         Field::new("sensor", DataType::String),
-        Field::new("valor_maximo", DataType::Float64),
-        Field::new("valor_medio", DataType::Float64),
-        Field::new("valor_minimo", DataType::Float64),
-    ]);
+    ];
+    fields.extend(
+        metrics
+            .iter()
+            .map(|m| Field::new(&m.column_name(), DataType::Float64)),
+    );
 
-    Ok(DataFrame::empty_with_schema(&schema))
+    Ok(DataFrame::empty_with_schema(&Schema::from_iter(fields)))
 }
 
 /// Convert a Polars DataFrame to our SensorAggregation format
-fn dataframe_to_aggregations(df: DataFrame) -> Result<Vec<SensorAggregation>> {
+fn dataframe_to_aggregations(
+    df: DataFrame,
+    metrics: &[MetricSpec],
+) -> Result<Vec<SensorAggregation>> {
     let mut aggregations = Vec::new();
 
     let height = df.height();
@@ -312,23 +381,18 @@ fn dataframe_to_aggregations(df: DataFrame) -> Result<Vec<SensorAggregation>> {
         .str()
         .with_context(|| "Sensor column is not string type")?;
 
-    let max_col = df
-        .column("valor_maximo")
-        .with_context(|| "Missing valor_maximo column")?
-        .f64()
-        .with_context(|| "valor_maximo column is not f64 type")?;
-
-    let mean_col = df
-        .column("valor_medio")
-        .with_context(|| "Missing valor_medio column")?
-        .f64()
-        .with_context(|| "valor_medio column is not f64 type")?;
-
-    let min_col = df
-        .column("valor_minimo")
-        .with_context(|| "Missing valor_minimo column")?
-        .f64()
-        .with_context(|| "valor_minimo column is not f64 type")?;
+    let metric_cols: Vec<(String, &ChunkedArray<Float64Type>)> = metrics
+        .iter()
+        .map(|m| {
+            let name = m.column_name();
+            let column = df
+                .column(&name)
+                .with_context(|| format!("Missing {} column", name))?
+                .f64()
+                .with_context(|| format!("{} column is not f64 type", name))?;
+            Ok((name, column))
+        })
+        .collect::<Result<Vec<_>>>()?;
 
     // Convert each row to SensorAggregation
     for i in 0..height {
@@ -347,41 +411,289 @@ fn dataframe_to_aggregations(df: DataFrame) -> Result<Vec<SensorAggregation>> {
             .map(|v| v.to_string())
             .unwrap_or_else(|| "unknown".to_string());
 
-        let max_value = max_col.get(i).unwrap_or(0.0);
-        let mean_value = mean_col.get(i).unwrap_or(0.0);
-        let min_value = min_col.get(i).unwrap_or(0.0);
+        let values = metric_cols
+            .iter()
+            .map(|(name, column)| (name.clone(), column.get(i).unwrap_or(0.0)))
+            .collect();
 
         aggregations.push(SensorAggregation {
             device,
             year_month,
             sensor,
-            max_value,
-            mean_value,
-            min_value,
+            values,
         });
     }
 
     Ok(aggregations)
 }
 
-/// Convert analysis results to CSV format
-pub fn results_to_csv(results: &[AnalysisResults]) -> String {
-    let mut csv_output =
-        String::from("device,ano-mes,sensor,valor_maximo,valor_medio,valor_minimo\n");
+/// Analyze a Parquet sensor file directly via Polars' lazy engine, skipping the
+/// mmap + manual line-splitting pipeline entirely.
+pub fn analyze_parquet_file(path: &str, metrics: &[MetricSpec]) -> Result<AnalysisResults> {
+    let start_time = Instant::now();
+
+    let processed_df = process_parquet_lazyframe(path, metrics)?;
+    // Count the input rows, not the aggregated rows `processed_df` holds.
+    let file = std::fs::File::open(path)
+        .with_context(|| format!("Failed to open parquet file: {}", path))?;
+    let total_lines_processed = ParquetReader::new(file)
+        .num_rows()
+        .with_context(|| format!("Failed to read parquet row count: {}", path))?;
+    let aggregations = dataframe_to_aggregations(processed_df, metrics)?;
+
+    Ok(AnalysisResults {
+        aggregations,
+        total_lines_processed,
+        processing_time_ms: start_time.elapsed().as_millis() as f64,
+    })
+}
+
+/// Build and collect the Parquet analysis pipeline, keeping every step lazy until
+/// the very end. The `data >= 2024-03-01` filter runs on the raw stored string
+/// column, before it's overwritten by the `with_columns` datetime rewrite below,
+/// so the parquet scan can still push the predicate down and skip row groups
+/// entirely before this date.
+fn process_parquet_lazyframe(path: &str, metrics: &[MetricSpec]) -> Result<DataFrame> {
+    let mut lf = LazyFrame::scan_parquet(path, ScanArgsParquet::default())
+        .with_context(|| format!("Failed to scan parquet file: {}", path))?;
+
+    let schema = lf
+        .schema()
+        .with_context(|| "Failed to read parquet schema")?;
+    let has_column = |name: &str| schema.iter_names().any(|n| n.as_str() == name);
+
+    let columns_to_drop: Vec<&str> = ["id", "latitude", "longitude"]
+        .into_iter()
+        .filter(|&name| has_column(name))
+        .collect();
+    if !columns_to_drop.is_empty() {
+        lf = lf.drop_columns(columns_to_drop);
+    }
+
+    if !has_column("device") {
+        println!("Warning: Parquet file does not contain 'device' column");
+        return create_empty_result_dataframe(metrics);
+    }
+
+    lf = lf.filter(col("device").is_not_null());
+
+    // Filter on the raw stored string column (e.g. "2024-03-01 12:34:56")
+    // before it's parsed below: lexicographic comparison against an
+    // ISO-formatted date prefix agrees with chronological order, so this is
+    // still a comparison against the literal raw column the parquet scan can
+    // push down and skip row groups entirely before this date.
+    let filter_date = NaiveDate::from_ymd_opt(2024, 3, 1)
+        .ok_or_else(|| anyhow::anyhow!("Invalid filter date"))?;
+    lf = lf.filter(col("data").gt_eq(lit(filter_date.format("%Y-%m-%d").to_string())));
+
+    // Overwrite the 'data' column with parsed Datetime (matching the CSV path)
+    lf = lf.with_columns([col("data")
+        .str()
+        .split(lit(" "))
+        .list()
+        .get(lit(0), true)
+        .str()
+        .strptime(
+            DataType::Datetime(TimeUnit::Milliseconds, None),
+            StrptimeOptions {
+                format: Some("%Y-%m-%d".to_string()),
+                ..Default::default()
+            },
+            lit("raise"),
+        )
+        .alias("data")]);
+
+    lf = lf.with_columns([col("data").dt().strftime("%Y-%m").alias("ano-mes")]);
+
+    let sensor_columns: Vec<&str> = [
+        "temperatura",
+        "umidade",
+        "luminosidade",
+        "ruido",
+        "eco2",
+        "etvoc",
+    ]
+    .iter()
+    .filter(|&&name| has_column(name))
+    .copied()
+    .collect();
+
+    if sensor_columns.is_empty() {
+        println!("Warning: No sensor columns found");
+        return create_empty_result_dataframe(metrics);
+    }
+
+    let mut cast_expressions = Vec::new();
+    for &sensor in &sensor_columns {
+        let dtype = schema
+            .get(sensor)
+            .ok_or_else(|| anyhow::anyhow!("Failed to read dtype for column: {}", sensor))?;
+        if dtype != &DataType::Float64 {
+            cast_expressions.push(col(sensor).cast(DataType::Float64));
+        } else {
+            cast_expressions.push(col(sensor));
+        }
+    }
+    lf = lf.with_columns(cast_expressions);
+
+    let all_columns_after_cast: Vec<Expr> =
+        lf.schema()?.iter_fields().map(|f| col(f.name())).collect();
+    lf = lf.drop_nulls(Some(all_columns_after_cast));
+
+    lf = lf.with_columns([
+        col("device").cast(DataType::Categorical(None, CategoricalOrdering::Physical)),
+        col("ano-mes").cast(DataType::Categorical(None, CategoricalOrdering::Physical)),
+    ]);
+
+    let mut result_dfs = Vec::new();
+    for &sensor in &sensor_columns {
+        let agg_exprs: Vec<Expr> = metrics.iter().map(|m| metric_expr(sensor, m)).collect();
+        let sensor_lf = lf
+            .clone()
+            .group_by([col("device"), col("ano-mes")])
+            .agg(agg_exprs)
+            .with_columns([lit(sensor)
+                .cast(DataType::Categorical(None, CategoricalOrdering::Physical))
+                .alias("sensor")]);
+
+        result_dfs.push(sensor_lf);
+    }
+
+    let mut select_exprs = vec![
+        col("device").cast(DataType::String),
+        col("ano-mes").cast(DataType::String),
+        col("sensor").cast(DataType::String),
+    ];
+    select_exprs.extend(metrics.iter().map(|m| col(&m.column_name())));
+
+    let final_result = concat(result_dfs, UnionArgs::default())
+        .with_context(|| "Failed to concatenate sensor results")?
+        .sort(
+            ["device", "ano-mes", "sensor"],
+            SortMultipleOptions::default(),
+        )
+        .select(select_exprs);
+
+    final_result
+        .collect()
+        .with_context(|| "Failed to collect final results")
+}
+
+/// Write merged analysis results as a Parquet file via `ParquetWriter`
+pub fn results_to_parquet(results: &[AnalysisResults], path: &str, metrics: &[MetricSpec]) -> Result<()> {
+    let mut devices = Vec::new();
+    let mut year_months = Vec::new();
+    let mut sensors = Vec::new();
+    let mut metric_columns: Vec<Vec<f64>> = metrics.iter().map(|_| Vec::new()).collect();
+
+    for result in results {
+        for agg in &result.aggregations {
+            devices.push(agg.device.clone());
+            year_months.push(agg.year_month.clone());
+            sensors.push(agg.sensor.clone());
+            for (column, metric) in metric_columns.iter_mut().zip(metrics) {
+                let value = agg
+                    .values
+                    .iter()
+                    .find(|(name, _)| name == &metric.column_name())
+                    .map(|(_, value)| *value)
+                    .unwrap_or(0.0);
+                column.push(value);
+            }
+        }
+    }
+
+    let mut series_vec = vec![
+        Series::new("device", devices),
+        Series::new("ano-mes", year_months),
+        Series::new("sensor", sensors),
+    ];
+    for (metric, values) in metrics.iter().zip(metric_columns) {
+        series_vec.push(Series::new(&metric.column_name(), values));
+    }
+
+    let mut df = DataFrame::new(series_vec)
+        .with_context(|| "Failed to build aggregations DataFrame")?;
+
+    let file = std::fs::File::create(path)
+        .with_context(|| format!("Failed to create parquet output file: {}", path))?;
+
+    ParquetWriter::new(file)
+        .finish(&mut df)
+        .with_context(|| format!("Failed to write parquet file: {}", path))?;
+
+    Ok(())
+}
+
+/// Convert analysis results to CSV format, generating the header dynamically
+/// from whichever metrics were requested.
+pub fn results_to_csv(results: &[AnalysisResults], metrics: &[MetricSpec]) -> String {
+    let metric_columns: Vec<String> = metrics.iter().map(|m| m.column_name()).collect();
+    let mut csv_output = format!("device,ano-mes,sensor,{}\n", metric_columns.join(","));
 
     for result in results {
         for agg in &result.aggregations {
+            let values: Vec<String> = metric_columns
+                .iter()
+                .map(|name| {
+                    agg.values
+                        .iter()
+                        .find(|(value_name, _)| value_name == name)
+                        .map(|(_, value)| value.to_string())
+                        .unwrap_or_else(|| "0".to_string())
+                })
+                .collect();
+
             csv_output.push_str(&format!(
-                "{},{},{},{},{},{}\n",
+                "{},{},{},{}\n",
                 agg.device,
                 agg.year_month,
                 agg.sensor,
-                agg.max_value,
-                agg.mean_value,
-                agg.min_value
+                values.join(",")
             ));
         }
     }
 
     csv_output
 }
+
+/// One aggregation row, reshaped for JSON: `values` (an ordered
+/// `Vec<(String, f64)>` in the Rust types) is flattened into per-metric
+/// fields so a consumer can read `row.valor_medio` directly.
+#[derive(serde::Serialize)]
+struct AggregationJson {
+    device: String,
+    year_month: String,
+    sensor: String,
+    #[serde(flatten)]
+    values: std::collections::BTreeMap<String, f64>,
+}
+
+/// Machine-readable run report: the aggregations plus the phase timing
+/// breakdown, for `--output-format json` / `--stats-json`.
+#[derive(serde::Serialize)]
+struct RunReport<'a> {
+    stats: &'a crate::types::ProcessingStats,
+    aggregations: Vec<AggregationJson>,
+}
+
+/// Serialize analysis results and run statistics as a single JSON document.
+pub fn results_to_json(
+    results: &[AnalysisResults],
+    stats: &crate::types::ProcessingStats,
+) -> Result<String> {
+    let aggregations = results
+        .iter()
+        .flat_map(|result| result.aggregations.iter())
+        .map(|agg| AggregationJson {
+            device: agg.device.clone(),
+            year_month: agg.year_month.clone(),
+            sensor: agg.sensor.clone(),
+            values: agg.values.iter().cloned().collect(),
+        })
+        .collect();
+
+    let report = RunReport { stats, aggregations };
+
+    serde_json::to_string_pretty(&report).with_context(|| "Failed to serialize results to JSON")
+}
@@ -0,0 +1,199 @@
+use crate::types::{AnalysisResults, MetricSpec, ProcessingConfig, SensorAggregation};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// A single deduplicated aggregation row, ready to be persisted. `values`
+/// holds one `(column_name, value)` pair per requested `MetricSpec`, in the
+/// order the metrics were requested, mirroring `SensorAggregation` itself —
+/// so a metric that wasn't computed for this run simply has no entry here,
+/// rather than showing up as a fabricated `0.0`.
+#[derive(Debug, Clone, Serialize)]
+pub struct AggregationRecord {
+    pub device: String,
+    pub year_month: String,
+    pub sensor: String,
+    pub values: Vec<(String, f64)>,
+}
+
+/// Merge aggregations from every worker's `AnalysisResults`, deduplicating
+/// rows that share a `(device, year_month, sensor)` key so the output is a
+/// single canonical table regardless of how chunks were partitioned. Later
+/// results win when a key repeats.
+fn merge_aggregation_records(
+    results: &[AnalysisResults],
+    metrics: &[MetricSpec],
+) -> Vec<AggregationRecord> {
+    let mut merged: BTreeMap<(String, String, String), AggregationRecord> = BTreeMap::new();
+
+    for result in results {
+        for agg in &result.aggregations {
+            let key = (agg.device.clone(), agg.year_month.clone(), agg.sensor.clone());
+            merged.insert(key, aggregation_to_record(agg, metrics));
+        }
+    }
+
+    merged.into_values().collect()
+}
+
+fn metric_value(agg: &SensorAggregation, column_name: &str) -> Option<f64> {
+    agg.values
+        .iter()
+        .find(|(name, _)| name == column_name)
+        .map(|(_, value)| *value)
+}
+
+fn aggregation_to_record(agg: &SensorAggregation, metrics: &[MetricSpec]) -> AggregationRecord {
+    AggregationRecord {
+        device: agg.device.clone(),
+        year_month: agg.year_month.clone(),
+        sensor: agg.sensor.clone(),
+        values: metrics
+            .iter()
+            .filter_map(|m| {
+                let name = m.column_name();
+                metric_value(agg, &name).map(|value| (name, value))
+            })
+            .collect(),
+    }
+}
+
+/// Write the merged aggregations as `device|year_month|sensor|<metric...>`
+/// rows (or whatever `config.delimiter` is) to `path`, with one column per
+/// `metrics` entry; a metric with no computed value for a row is left blank.
+pub fn write_aggregations_csv(
+    results: &[AnalysisResults],
+    path: &str,
+    config: &ProcessingConfig,
+    metrics: &[MetricSpec],
+) -> Result<()> {
+    let records = merge_aggregation_records(results, metrics);
+    let d = config.delimiter;
+
+    let metric_columns: Vec<String> = metrics.iter().map(|m| m.column_name()).collect();
+    let mut output = format!("device{d}year_month{d}sensor{d}{}\n", metric_columns.join(&d.to_string()));
+    for record in &records {
+        let values: Vec<String> = metric_columns
+            .iter()
+            .map(|name| {
+                record
+                    .values
+                    .iter()
+                    .find(|(value_name, _)| value_name == name)
+                    .map(|(_, value)| value.to_string())
+                    .unwrap_or_default()
+            })
+            .collect();
+
+        output.push_str(&format!(
+            "{}{d}{}{d}{}{d}{}\n",
+            record.device,
+            record.year_month,
+            record.sensor,
+            values.join(&d.to_string())
+        ));
+    }
+
+    std::fs::write(path, output)
+        .with_context(|| format!("Failed to write aggregation CSV to '{}'", path))
+}
+
+/// Write the merged aggregations as a JSON array of records to `path`.
+pub fn write_aggregations_json(
+    results: &[AnalysisResults],
+    path: &str,
+    _config: &ProcessingConfig,
+    metrics: &[MetricSpec],
+) -> Result<()> {
+    let records = merge_aggregation_records(results, metrics);
+    let json = serde_json::to_string_pretty(&records)
+        .with_context(|| "Failed to serialize aggregations to JSON")?;
+
+    std::fs::write(path, json)
+        .with_context(|| format!("Failed to write aggregation JSON to '{}'", path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn results_with_duplicate_key() -> Vec<AnalysisResults> {
+        let agg = |max: f64| SensorAggregation {
+            device: "dev1".to_string(),
+            year_month: "2024-04".to_string(),
+            sensor: "temperatura".to_string(),
+            values: vec![
+                ("valor_maximo".to_string(), max),
+                ("valor_medio".to_string(), 20.0),
+                ("valor_minimo".to_string(), 10.0),
+            ],
+        };
+
+        vec![
+            AnalysisResults {
+                aggregations: vec![agg(25.0)],
+                total_lines_processed: 2,
+                processing_time_ms: 1.0,
+            },
+            AnalysisResults {
+                aggregations: vec![agg(30.0)],
+                total_lines_processed: 2,
+                processing_time_ms: 1.0,
+            },
+        ]
+    }
+
+    fn default_metrics() -> Vec<MetricSpec> {
+        vec![MetricSpec::Min, MetricSpec::Mean, MetricSpec::Max]
+    }
+
+    #[test]
+    fn test_merge_deduplicates_shared_key() {
+        let records = merge_aggregation_records(&results_with_duplicate_key(), &default_metrics());
+        assert_eq!(records.len(), 1);
+        assert_eq!(
+            records[0]
+                .values
+                .iter()
+                .find(|(name, _)| name == "valor_maximo")
+                .map(|(_, value)| *value),
+            Some(30.0)
+        );
+    }
+
+    #[test]
+    fn test_write_aggregations_csv_respects_delimiter() {
+        let results = results_with_duplicate_key();
+        let config = ProcessingConfig {
+            delimiter: ';',
+            ..Default::default()
+        };
+
+        let temp_file = tempfile::NamedTempFile::new().unwrap();
+        let path = temp_file.path().to_str().unwrap();
+
+        write_aggregations_csv(&results, path, &config, &default_metrics()).unwrap();
+        let contents = std::fs::read_to_string(path).unwrap();
+
+        assert!(contents.starts_with("device;year_month;sensor;valor_minimo;valor_medio;valor_maximo\n"));
+        assert_eq!(contents.lines().count(), 2);
+    }
+
+    #[test]
+    fn test_write_aggregations_csv_leaves_uncomputed_metrics_blank() {
+        let results = results_with_duplicate_key();
+        let config = ProcessingConfig::default();
+        let metrics = vec![MetricSpec::Std, MetricSpec::Median];
+
+        let temp_file = tempfile::NamedTempFile::new().unwrap();
+        let path = temp_file.path().to_str().unwrap();
+
+        write_aggregations_csv(&results, path, &config, &metrics).unwrap();
+        let contents = std::fs::read_to_string(path).unwrap();
+
+        // Neither metric was actually computed in `results_with_duplicate_key`,
+        // so both columns must be present but blank, never a fabricated 0.0.
+        assert!(contents.starts_with("device|year_month|sensor|valor_desvio_padrao|valor_mediana\n"));
+        assert!(contents.lines().nth(1).unwrap().ends_with("||"));
+    }
+}
@@ -1,4 +1,5 @@
-use crate::types::{CsvChunk, DeviceHashTable, ProcessingConfig};
+use crate::file_mapping::{DeviceIndex, MappedCsvFile};
+use crate::types::{CsvChunk, DeviceHashTable, MetricSpec, ProcessingConfig};
 use anyhow::Result;
 use ahash::AHashMap;
 
@@ -6,17 +7,39 @@ use ahash::AHashMap;
 pub fn build_device_hash_table<'a>(
     data: &'a str,
     device_column_index: usize,
-    _header: &str,
+    header: &str,
     config: &ProcessingConfig,
 ) -> Result<DeviceHashTable<'a>> {
     let estimated_lines = data.lines().count();
     let mut hash_table: DeviceHashTable<'a> = AHashMap::with_capacity(estimated_lines / 2);
     let delimiter = config.delimiter as u8;
+
+    // Resolve the filter's target column once, outside the hot loop.
+    let filter_column_index = match &config.row_filter {
+        Some(filter) => Some(
+            header
+                .split(config.delimiter)
+                .position(|col| col.trim() == filter.column_name())
+                .ok_or_else(|| {
+                    anyhow::anyhow!("Filter column '{}' not found in header", filter.column_name())
+                })?,
+        ),
+        None => None,
+    };
+
     for line in data.lines() {
         let line = line; // no trim for performance
         if line.is_empty() {
             continue;
         }
+
+        if let (Some(filter), Some(filter_idx)) = (&config.row_filter, filter_column_index) {
+            let field_value = line.split(config.delimiter).nth(filter_idx).unwrap_or("");
+            if !filter.matches(field_value) {
+                continue;
+            }
+        }
+
         let bytes = line.as_bytes();
         let mut col_start = 0;
         let mut col_end = 0;
@@ -61,20 +84,21 @@ pub fn partition_by_device<'a>(
     hash_table: &DeviceHashTable<'a>,
     num_workers: usize,
     header: &str,
+    metrics: &[MetricSpec],
 ) -> Vec<CsvChunk> {
     if hash_table.is_empty() || num_workers == 0 {
         return Vec::new();
     }
-    
+
     // Collect all devices with their line counts
     let mut devices_with_counts: Vec<(String, usize)> = hash_table
         .iter()
         .map(|(k, v)| (k.clone(), v.len()))
         .collect();
-    
+
     // Sort by line count (descending) to enable better load balancing
     devices_with_counts.sort_by(|a, b| b.1.cmp(&a.1));
-    
+
     // Initialize workers with empty chunks
     let mut worker_chunks: Vec<CsvChunk> = (0..num_workers)
         .map(|_| CsvChunk {
@@ -82,6 +106,7 @@ pub fn partition_by_device<'a>(
             header: header.to_string(),
             device_ids: Vec::new(),
             line_count: 0,
+            metrics: metrics.to_vec(),
         })
         .collect();
     
@@ -119,6 +144,68 @@ pub fn partition_by_device<'a>(
         .collect()
 }
 
+/// Partition devices across workers directly from a persisted `DeviceIndex`,
+/// using `get_device_slices` to borrow each device's lines straight from
+/// the mmap instead of re-scanning the file into a `DeviceHashTable` first.
+/// Line ordering within a device is preserved, and devices are still
+/// assigned with the same greedy least-loaded-worker strategy as
+/// `partition_by_device` for comparable load balance.
+pub fn partition_by_device_index(
+    index: &DeviceIndex,
+    mapped_file: &MappedCsvFile,
+    num_workers: usize,
+    header: &str,
+    metrics: &[MetricSpec],
+) -> Vec<CsvChunk> {
+    if num_workers == 0 {
+        return Vec::new();
+    }
+
+    let mut devices_with_counts: Vec<(String, usize)> = index
+        .devices()
+        .map(|device_id| (device_id.clone(), index.line_count(device_id)))
+        .collect();
+    devices_with_counts.sort_by(|a, b| b.1.cmp(&a.1));
+
+    let mut worker_chunks: Vec<CsvChunk> = (0..num_workers)
+        .map(|_| CsvChunk {
+            data: String::new(),
+            header: header.to_string(),
+            device_ids: Vec::new(),
+            line_count: 0,
+            metrics: metrics.to_vec(),
+        })
+        .collect();
+
+    let mut worker_loads = vec![0usize; num_workers];
+
+    for (device_id, line_count) in devices_with_counts {
+        let min_worker_idx = worker_loads
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, &load)| load)
+            .map(|(idx, _)| idx)
+            .unwrap_or(0);
+
+        let lines = mapped_file.get_device_slices(index, &device_id);
+        let chunk = &mut worker_chunks[min_worker_idx];
+        for line in &lines {
+            if !chunk.data.is_empty() {
+                chunk.data.push('\n');
+            }
+            chunk.data.push_str(line);
+        }
+        chunk.device_ids.push(device_id);
+        chunk.line_count += line_count;
+        worker_loads[min_worker_idx] += line_count;
+    }
+
+    worker_chunks
+        .into_iter()
+        .filter(|chunk| chunk.line_count > 0)
+        .collect()
+}
+
 /// Calculate load balancing statistics
 pub fn calculate_load_balance_stats(chunks: &[CsvChunk]) -> (f64, usize, usize) {
     if chunks.is_empty() {
@@ -163,10 +250,39 @@ mod tests {
         let mut hash_table: DeviceHashTable = AHashMap::new();
         hash_table.insert("dev1".to_string(), vec!["line1", "line2"]);
         hash_table.insert("dev2".to_string(), vec!["line3"]);
-        let chunks = partition_by_device(&hash_table, 2, "header");
+        let chunks = partition_by_device(&hash_table, 2, "header", &crate::types::default_metrics());
         assert_eq!(chunks.len(), 2);
         assert_eq!(chunks.iter().map(|c| c.line_count).sum::<usize>(), 3);
     }
+    #[test]
+    fn test_partition_by_device_index() -> Result<()> {
+        use std::io::Write;
+        use tempfile::NamedTempFile;
+
+        let mut temp_file = NamedTempFile::new()?;
+        writeln!(temp_file, "id|device|temp|hum")?;
+        writeln!(temp_file, "1|dev1|23.5|45.2")?;
+        writeln!(temp_file, "2|dev1|24.1|46.8")?;
+        writeln!(temp_file, "3|dev2|22.8|44.5")?;
+
+        let mapped_file = crate::file_mapping::MappedCsvFile::new(temp_file.path())?;
+        let index = mapped_file.build_device_index(1, '|')?;
+
+        let chunks = partition_by_device_index(
+            &index,
+            &mapped_file,
+            2,
+            &mapped_file.header,
+            &crate::types::default_metrics(),
+        );
+
+        assert_eq!(chunks.iter().map(|c| c.line_count).sum::<usize>(), 3);
+        assert!(chunks.iter().any(|c| c.device_ids.contains(&"dev1".to_string())));
+        assert!(chunks.iter().any(|c| c.device_ids.contains(&"dev2".to_string())));
+
+        Ok(())
+    }
+
     #[test]
     fn test_load_balance_stats() {
         let chunks = vec![
@@ -175,15 +291,17 @@ mod tests {
                 header: String::new(),
                 device_ids: vec![],
                 line_count: 10,
+                metrics: crate::types::default_metrics(),
             },
             CsvChunk {
                 data: String::new(),
                 header: String::new(),
                 device_ids: vec![],
                 line_count: 20,
+                metrics: crate::types::default_metrics(),
             },
         ];
-        
+
         let (imbalance, min_load, max_load) = calculate_load_balance_stats(&chunks);
         assert_eq!(min_load, 10);
         assert_eq!(max_load, 20);
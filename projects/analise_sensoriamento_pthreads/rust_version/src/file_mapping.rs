@@ -1,7 +1,68 @@
 use anyhow::{Context, Result};
 use memmap2::Mmap;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
 use std::fs::File;
 use std::path::Path;
+use sysinfo::System;
+
+/// Below this many bytes per chunk, thread-spawn overhead stops being
+/// worth it, so `adaptive_chunk_plan` never targets chunks smaller than
+/// this and `ChunkPlan::chunk_count` hands out fewer chunks instead.
+const MIN_CHUNK_BYTES: usize = 1024 * 1024; // 1 MiB
+
+/// A cap on parallelism: beyond this many workers, returns from extra
+/// threads tend to be dominated by contention on shared I/O/allocator
+/// paths rather than real speedup.
+const MAX_ADAPTIVE_THREADS: usize = 8;
+
+/// A thread count and target chunk size, sized to the machine this process
+/// is running on.
+#[derive(Debug, Clone, Copy)]
+pub struct ChunkPlan {
+    pub num_threads: usize,
+    pub target_chunk_bytes: usize,
+}
+
+impl ChunkPlan {
+    /// Number of chunks to ask `split_into_chunks` for, given `data_len`
+    /// bytes of input: enough chunks to honor `target_chunk_bytes`,
+    /// capped at `num_threads`.
+    pub fn chunk_count(&self, data_len: usize) -> usize {
+        if data_len == 0 || self.target_chunk_bytes == 0 {
+            return 1;
+        }
+        let by_size = (data_len + self.target_chunk_bytes - 1) / self.target_chunk_bytes;
+        by_size.clamp(1, self.num_threads.max(1))
+    }
+}
+
+/// Choose a thread count and target chunk size from this machine's
+/// available RAM and core count, so `split_into_chunks`/`par_records`
+/// auto-tune instead of the caller hand-picking `n`. Threads are capped at
+/// `min(cores, MAX_ADAPTIVE_THREADS)`, and the target chunk size is kept
+/// at least `MIN_CHUNK_BYTES` so thread-spawn overhead stays amortized,
+/// while staying within a quarter of available memory so `num_threads`
+/// chunks in flight at once don't pressure the allocator.
+pub fn adaptive_chunk_plan(data_len: usize) -> ChunkPlan {
+    let cores = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1);
+    let num_threads = cores.min(MAX_ADAPTIVE_THREADS).max(1);
+
+    let mut system = System::new();
+    system.refresh_memory();
+    let available_bytes = system.available_memory() as usize;
+    let memory_ceiling = (available_bytes / 4).max(MIN_CHUNK_BYTES);
+
+    let even_share = if num_threads == 0 { data_len } else { data_len / num_threads };
+    let target_chunk_bytes = even_share.clamp(MIN_CHUNK_BYTES, memory_ceiling);
+
+    ChunkPlan {
+        num_threads,
+        target_chunk_bytes,
+    }
+}
 
 /// Memory-mapped CSV file
 pub struct MappedCsvFile {
@@ -11,6 +72,215 @@ pub struct MappedCsvFile {
     pub data_start_offset: usize,
 }
 
+/// Per-device byte ranges (`start..end` into the mapped file, not relative
+/// to `data_start_offset`) recording where each device's lines live.
+/// Building this once lets `get_device_slices` extract a single device
+/// without rescanning the rest of the file, and lets repeat runs over the
+/// same file skip the scan entirely by loading a persisted copy.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct DeviceIndex {
+    ranges: BTreeMap<String, Vec<(usize, usize)>>,
+}
+
+/// A single CSV record borrowed from the mmap. Without `quote`, fields are
+/// split from the underlying line lazily, on demand, so scanning a record
+/// without reading every column never touches the allocator; in quoted
+/// mode `fields` collects every field up front. When `quote` is set, fields
+/// are parsed RFC 4180-style: a quoted field may contain the delimiter
+/// literally, and a doubled quote (`""`) collapses to one escaped quote.
+#[derive(Debug, Clone, Copy)]
+pub struct Record<'a> {
+    line: &'a str,
+    delimiter: char,
+    quote: Option<char>,
+}
+
+impl<'a> Record<'a> {
+    /// Field slices, split lazily from the underlying line. Borrowed with
+    /// no copy unless quoting is enabled and this particular field needed
+    /// unescaping, in which case it comes back as an owned `Cow::Owned`.
+    pub fn fields(&self) -> FieldsIter<'a> {
+        match self.quote {
+            Some(quote) => FieldsIter::Quoted(
+                split_quoted_raw(self.line, self.delimiter, quote)
+                    .into_iter()
+                    .map(move |raw| unescape_field(raw, quote))
+                    .collect::<Vec<_>>()
+                    .into_iter(),
+            ),
+            None => FieldsIter::Plain(self.line.split(self.delimiter)),
+        }
+    }
+
+    /// The field at `index`, or `None` if the record has fewer fields.
+    pub fn field(&self, index: usize) -> Option<Cow<'a, str>> {
+        self.fields().nth(index)
+    }
+
+    /// The full, unsplit line (or, in quoted mode, multi-line record) this
+    /// `Record` was borrowed from.
+    pub fn raw(&self) -> &'a str {
+        self.line
+    }
+}
+
+/// Iterator returned by `Record::fields`. Plain mode splits lazily with no
+/// allocation; quoted mode unescapes fields up front since a quoted field
+/// can span what `split` would otherwise treat as multiple fields.
+pub enum FieldsIter<'a> {
+    Plain(std::str::Split<'a, char>),
+    Quoted(std::vec::IntoIter<Cow<'a, str>>),
+}
+
+impl<'a> Iterator for FieldsIter<'a> {
+    type Item = Cow<'a, str>;
+
+    fn next(&mut self) -> Option<Cow<'a, str>> {
+        match self {
+            FieldsIter::Plain(iter) => iter.next().map(Cow::Borrowed),
+            FieldsIter::Quoted(iter) => iter.next(),
+        }
+    }
+}
+
+/// Split `line` on `delimiter`, treating `delimiter` (and `quote` itself)
+/// as literal data while inside an open quoted field. Returned slices
+/// still carry their surrounding quotes, if any; `unescape_field` strips
+/// those in a second pass.
+fn split_quoted_raw(line: &str, delimiter: char, quote: char) -> Vec<&str> {
+    let mut fields = Vec::new();
+    let mut start = 0;
+    let mut in_quotes = false;
+
+    for (byte_idx, c) in line.char_indices() {
+        if c == quote {
+            in_quotes = !in_quotes;
+        } else if c == delimiter && !in_quotes {
+            fields.push(&line[start..byte_idx]);
+            start = byte_idx + c.len_utf8();
+        }
+    }
+    fields.push(&line[start..]);
+
+    fields
+}
+
+/// Strip a field's surrounding quotes (if any) and collapse doubled quotes
+/// (`""`) into one literal quote. Fields that were never quoted are
+/// returned borrowed, with no copy.
+fn unescape_field(raw: &str, quote: char) -> Cow<'_, str> {
+    let quote_len = quote.len_utf8();
+    if raw.len() < 2 * quote_len || !raw.starts_with(quote) || !raw.ends_with(quote) {
+        return Cow::Borrowed(raw);
+    }
+
+    let inner = &raw[quote_len..raw.len() - quote_len];
+    let doubled: String = std::iter::repeat(quote).take(2).collect();
+    if inner.contains(doubled.as_str()) {
+        Cow::Owned(inner.replace(doubled.as_str(), &quote.to_string()))
+    } else {
+        Cow::Borrowed(inner)
+    }
+}
+
+/// Iterator behind `MappedCsvFile::records_quoted`: walks `remaining`
+/// looking for a `\n` that sits outside an open quoted field, re-checking
+/// parity from the start of the unconsumed data each time a candidate
+/// newline turns out to be inside quotes.
+struct QuotedRecordIter<'a> {
+    remaining: &'a str,
+    delimiter: char,
+    quote: char,
+}
+
+impl<'a> Iterator for QuotedRecordIter<'a> {
+    type Item = Record<'a>;
+
+    fn next(&mut self) -> Option<Record<'a>> {
+        loop {
+            if self.remaining.is_empty() {
+                return None;
+            }
+
+            let mut search_from = 0;
+            let line_end = loop {
+                match self.remaining[search_from..].find('\n') {
+                    Some(rel_pos) => {
+                        let pos = search_from + rel_pos;
+                        if ends_inside_quotes(&self.remaining[..pos], self.quote) {
+                            search_from = pos + 1;
+                            continue;
+                        }
+                        break pos;
+                    }
+                    None => break self.remaining.len(),
+                }
+            };
+
+            let line = &self.remaining[..line_end];
+            self.remaining = self.remaining.get(line_end + 1..).unwrap_or("");
+
+            if line.is_empty() {
+                continue;
+            }
+
+            return Some(Record {
+                line,
+                delimiter: self.delimiter,
+                quote: Some(self.quote),
+            });
+        }
+    }
+}
+
+/// Returns `true` if, after scanning `text`, we're left inside an open
+/// (unterminated) quoted field — i.e. an odd number of `quote` characters
+/// were seen. A doubled quote (`""`) inside a field still nets to "no
+/// change" in parity across the pair, since a newline can never fall
+/// between the two characters of that pair, so this cheap toggle count is
+/// sufficient for deciding whether a given `\n` is literal data.
+fn ends_inside_quotes(text: &str, quote: char) -> bool {
+    text.chars().filter(|&c| c == quote).count() % 2 == 1
+}
+
+/// Iterator behind `MappedCsvFile::records_with_progress`: wraps any
+/// `Record` iterator and reports `(bytes_done, total_bytes)` to
+/// `on_progress` after each record, so a batch job can render throughput
+/// and ETA without `MappedCsvFile` itself depending on any progress-bar
+/// crate.
+struct ProgressRecordIter<I, F> {
+    inner: I,
+    total_bytes: usize,
+    bytes_done: usize,
+    on_progress: F,
+}
+
+impl<'a, I, F> Iterator for ProgressRecordIter<I, F>
+where
+    I: Iterator<Item = Record<'a>>,
+    F: FnMut(usize, usize),
+{
+    type Item = Record<'a>;
+
+    fn next(&mut self) -> Option<Record<'a>> {
+        let record = self.inner.next()?;
+        self.bytes_done = (self.bytes_done + record.raw().len() + 1).min(self.total_bytes);
+        (self.on_progress)(self.bytes_done, self.total_bytes);
+        Some(record)
+    }
+}
+
+impl DeviceIndex {
+    /// Device IDs present in the index, in sorted order.
+    pub fn devices(&self) -> impl Iterator<Item = &String> {
+        self.ranges.keys()
+    }
+
+    pub fn line_count(&self, device_id: &str) -> usize {
+        self.ranges.get(device_id).map(Vec::len).unwrap_or(0)
+    }
+}
+
 impl MappedCsvFile {
     /// Map a CSV file into memory and parse the header
     pub fn new<P: AsRef<Path>>(file_path: P) -> Result<Self> {
@@ -67,6 +337,235 @@ impl MappedCsvFile {
             .with_context(|| "File contains invalid UTF-8")
     }
     
+    /// Build a per-device byte-range index in one pass over the data
+    /// region, recording each line's absolute offsets into the mmap.
+    pub fn build_device_index(
+        &self,
+        device_column_index: usize,
+        delimiter: char,
+    ) -> Result<DeviceIndex> {
+        let data = self.get_data()?;
+        let mut ranges: BTreeMap<String, Vec<(usize, usize)>> = BTreeMap::new();
+
+        let mut offset = self.data_start_offset;
+        for line in data.split('\n') {
+            let line_start = offset;
+            let line_end = offset + line.len();
+            offset = line_end + 1; // account for the '\n' this split consumed
+
+            if line.is_empty() {
+                continue;
+            }
+
+            if let Some(device_id) = line.split(delimiter).nth(device_column_index) {
+                if !device_id.is_empty() {
+                    ranges
+                        .entry(device_id.to_string())
+                        .or_default()
+                        .push((line_start, line_end));
+                }
+            }
+        }
+
+        Ok(DeviceIndex { ranges })
+    }
+
+    /// Load a previously-persisted device index from `sidecar_path` if it
+    /// exists and parses cleanly, building and persisting a fresh one
+    /// otherwise so the next run can skip the scan.
+    pub fn load_or_build_device_index(
+        &self,
+        sidecar_path: &Path,
+        device_column_index: usize,
+        delimiter: char,
+    ) -> Result<DeviceIndex> {
+        if let Ok(contents) = std::fs::read_to_string(sidecar_path) {
+            if let Ok(index) = serde_json::from_str(&contents) {
+                return Ok(index);
+            }
+        }
+
+        let index = self.build_device_index(device_column_index, delimiter)?;
+        let serialized =
+            serde_json::to_string(&index).with_context(|| "Failed to serialize device index")?;
+        std::fs::write(sidecar_path, serialized).with_context(|| {
+            format!(
+                "Failed to write device index to {}",
+                sidecar_path.display()
+            )
+        })?;
+
+        Ok(index)
+    }
+
+    /// Extract every line belonging to one device, borrowed directly from
+    /// the mmap with no copying.
+    pub fn get_device_slices<'a>(&'a self, index: &DeviceIndex, device_id: &str) -> Vec<&'a str> {
+        let Some(ranges) = index.ranges.get(device_id) else {
+            return Vec::new();
+        };
+
+        ranges
+            .iter()
+            .filter_map(|&(start, end)| std::str::from_utf8(&self.mmap[start..end]).ok())
+            .collect()
+    }
+
+    /// Divide the mapped data region into roughly `n` equal, newline-aligned
+    /// byte ranges so each can be handed to its own thread with zero
+    /// copying. Interior cut points at `i * len / n` are nudged forward to
+    /// the next `'\n'` so no record is ever split across two chunks; the
+    /// final chunk runs to EOF. Empty chunks (e.g. `n` exceeding the line
+    /// count) are dropped, so the result may have fewer than `n` entries.
+    pub fn split_into_chunks(&self, n: usize) -> Result<Vec<&str>> {
+        let data = self.get_data()?;
+        if n == 0 || data.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let len = data.len();
+        let mut boundaries = Vec::with_capacity(n + 1);
+        boundaries.push(0usize);
+
+        for i in 1..n {
+            let mut approx = (i * len / n).min(len);
+            while approx < len && !data.is_char_boundary(approx) {
+                approx += 1;
+            }
+            let boundary = match data[approx..].find('\n') {
+                Some(offset) => approx + offset + 1,
+                None => len,
+            };
+            boundaries.push(boundary);
+        }
+        boundaries.push(len);
+
+        Ok(boundaries
+            .windows(2)
+            .filter(|w| w[1] > w[0])
+            .map(|w| &data[w[0]..w[1]])
+            .collect())
+    }
+
+    /// Like `split_into_chunks`, but quote-aware: a candidate `'\n'` cut
+    /// point is skipped if it falls inside an open field quoted with
+    /// `quote` (there is no default; callers pass it, typically `"`), so a
+    /// chunk boundary never lands inside a quoted, embedded newline.
+    pub fn split_into_chunks_quoted(&self, n: usize, quote: char) -> Result<Vec<&str>> {
+        let data = self.get_data()?;
+        if n == 0 || data.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let len = data.len();
+        let mut boundaries = Vec::with_capacity(n + 1);
+        boundaries.push(0usize);
+
+        for i in 1..n {
+            let mut approx = (i * len / n).min(len);
+            while approx < len && !data.is_char_boundary(approx) {
+                approx += 1;
+            }
+            let mut search_from = approx;
+            let boundary = loop {
+                match data[search_from..].find('\n') {
+                    Some(offset) => {
+                        let pos = search_from + offset;
+                        if ends_inside_quotes(&data[..pos], quote) {
+                            search_from = pos + 1;
+                            continue;
+                        }
+                        break pos + 1;
+                    }
+                    None => break len,
+                }
+            };
+            boundaries.push(boundary);
+        }
+        boundaries.push(len);
+
+        Ok(boundaries
+            .windows(2)
+            .filter(|w| w[1] > w[0])
+            .map(|w| &data[w[0]..w[1]])
+            .collect())
+    }
+
+    /// Convenience wrapper around `split_into_chunks` that auto-tunes `n`
+    /// from the machine's available RAM and core count via
+    /// `adaptive_chunk_plan`, for callers that just want to fan whole-line
+    /// slices out over threads without hand-picking a count.
+    pub fn par_records(&self) -> Result<Vec<&str>> {
+        let data = self.get_data()?;
+        let plan = adaptive_chunk_plan(data.len());
+        self.split_into_chunks(plan.chunk_count(data.len()))
+    }
+
+    /// Like `par_records`, but routes through `split_into_chunks_quoted` so
+    /// a chunk boundary never lands inside an RFC 4180 quoted, embedded
+    /// newline.
+    pub fn par_records_quoted(&self, quote: char) -> Result<Vec<&str>> {
+        let data = self.get_data()?;
+        let plan = adaptive_chunk_plan(data.len());
+        self.split_into_chunks_quoted(plan.chunk_count(data.len()), quote)
+    }
+
+    /// Iterate over every data line as a `Record`, splitting fields only
+    /// when the caller asks for them rather than forcing every field into
+    /// an owned `String` up front the way `get_header_columns`/`get_data`
+    /// callers typically do.
+    pub fn records(&self, delimiter: char) -> Result<impl Iterator<Item = Record<'_>>> {
+        let data = self.get_data()?;
+        Ok(data.lines().filter(|line| !line.is_empty()).map(move |line| Record {
+            line,
+            delimiter,
+            quote: None,
+        }))
+    }
+
+    /// Like `records`, but quote-aware: a record is only terminated at a
+    /// `\n` that falls outside an open field quoted with `quote` (there is
+    /// no default; callers pass it, typically `"`), per RFC 4180, so an embedded newline inside quotes doesn't split one
+    /// logical record into two.
+    pub fn records_quoted(
+        &self,
+        delimiter: char,
+        quote: char,
+    ) -> Result<impl Iterator<Item = Record<'_>>> {
+        let data = self.get_data()?;
+        Ok(QuotedRecordIter {
+            remaining: data,
+            delimiter,
+            quote,
+        })
+    }
+
+    /// Like `records`, but calls `on_progress(bytes_done, total_bytes)`
+    /// after every record, where `total_bytes` is `mmap.len() -
+    /// data_start_offset` and `bytes_done` accumulates each record's byte
+    /// length (plus its trailing newline). Long scans over a
+    /// multi-gigabyte CSV otherwise give no feedback until they finish;
+    /// callers can feed the counts into an `indicatif` bar or a simple
+    /// `[SYSTEM]`-style print to show throughput and ETA, without this
+    /// parser taking a dependency on any UI crate.
+    pub fn records_with_progress<'a, F>(
+        &'a self,
+        delimiter: char,
+        on_progress: F,
+    ) -> Result<impl Iterator<Item = Record<'a>> + 'a>
+    where
+        F: FnMut(usize, usize) + 'a,
+    {
+        let total_bytes = self.mmap.len().saturating_sub(self.data_start_offset);
+        let inner = self.records(delimiter)?;
+        Ok(ProgressRecordIter {
+            inner,
+            total_bytes,
+            bytes_done: 0,
+            on_progress,
+        })
+    }
+
     /// Validate that the file has a .csv extension
     pub fn validate_csv_extension<P: AsRef<Path>>(file_path: P) -> bool {
         file_path.as_ref()
@@ -83,6 +582,25 @@ mod tests {
     use std::io::Write;
     use tempfile::NamedTempFile;
     
+    #[test]
+    fn test_adaptive_chunk_plan_caps_threads_and_chunk_size() {
+        let plan = adaptive_chunk_plan(100 * 1024 * 1024);
+        assert!(plan.num_threads >= 1);
+        assert!(plan.num_threads <= MAX_ADAPTIVE_THREADS);
+        assert!(plan.target_chunk_bytes >= MIN_CHUNK_BYTES);
+    }
+
+    #[test]
+    fn test_chunk_plan_chunk_count_bounded_by_threads() {
+        let plan = ChunkPlan {
+            num_threads: 4,
+            target_chunk_bytes: 1024,
+        };
+        assert_eq!(plan.chunk_count(0), 1);
+        assert_eq!(plan.chunk_count(512), 1);
+        assert_eq!(plan.chunk_count(1024 * 1024), 4);
+    }
+
     #[test]
     fn test_csv_extension_validation() {
         assert!(MappedCsvFile::validate_csv_extension("test.csv"));
@@ -107,7 +625,287 @@ mod tests {
         
         let columns = mapped.get_header_columns('|');
         assert_eq!(columns, vec!["id", "device", "temperature", "humidity"]);
-        
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_device_index_build_and_slice() -> Result<()> {
+        let mut temp_file = NamedTempFile::new()?;
+        writeln!(temp_file, "id|device|temperature|humidity")?;
+        writeln!(temp_file, "1|dev1|23.5|45.2")?;
+        writeln!(temp_file, "2|dev2|24.1|46.8")?;
+        writeln!(temp_file, "3|dev1|25.0|47.0")?;
+
+        let mapped = MappedCsvFile::new(temp_file.path())?;
+        let index = mapped.build_device_index(1, '|')?;
+
+        assert_eq!(index.line_count("dev1"), 2);
+        assert_eq!(index.line_count("dev2"), 1);
+        assert_eq!(index.devices().count(), 2);
+
+        let dev1_lines = mapped.get_device_slices(&index, "dev1");
+        assert_eq!(dev1_lines, vec!["1|dev1|23.5|45.2", "3|dev1|25.0|47.0"]);
+
+        assert!(mapped.get_device_slices(&index, "missing").is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_device_index_persists_to_sidecar() -> Result<()> {
+        let mut temp_file = NamedTempFile::new()?;
+        writeln!(temp_file, "id|device|temperature|humidity")?;
+        writeln!(temp_file, "1|dev1|23.5|45.2")?;
+        writeln!(temp_file, "2|dev2|24.1|46.8")?;
+
+        let mapped = MappedCsvFile::new(temp_file.path())?;
+
+        let sidecar = NamedTempFile::new()?;
+        let sidecar_path = sidecar.path().to_path_buf();
+        // NamedTempFile creates the file, but we want to exercise the
+        // build-and-persist path, so start from a path with nothing there.
+        std::fs::remove_file(&sidecar_path).ok();
+
+        let built = mapped.load_or_build_device_index(&sidecar_path, 1, '|')?;
+        assert!(sidecar_path.exists());
+
+        let loaded = mapped.load_or_build_device_index(&sidecar_path, 1, '|')?;
+        assert_eq!(built.line_count("dev1"), loaded.line_count("dev1"));
+        assert_eq!(built.line_count("dev2"), loaded.line_count("dev2"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_into_chunks_preserves_every_line_and_respects_boundaries() -> Result<()> {
+        let mut temp_file = NamedTempFile::new()?;
+        writeln!(temp_file, "id|device|value")?;
+        for i in 0..20 {
+            writeln!(temp_file, "{}|dev1|{}.0", i, i)?;
+        }
+
+        let mapped = MappedCsvFile::new(temp_file.path())?;
+        let chunks = mapped.split_into_chunks(4)?;
+
+        assert!(chunks.len() <= 4);
+
+        let reassembled_lines: usize = chunks
+            .iter()
+            .map(|chunk| chunk.lines().filter(|l| !l.is_empty()).count())
+            .sum();
+        assert_eq!(reassembled_lines, 20);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_into_chunks_more_chunks_than_lines_drops_empties() -> Result<()> {
+        let mut temp_file = NamedTempFile::new()?;
+        writeln!(temp_file, "id|device|value")?;
+        writeln!(temp_file, "1|dev1|1.0")?;
+        writeln!(temp_file, "2|dev1|2.0")?;
+
+        let mapped = MappedCsvFile::new(temp_file.path())?;
+        let chunks = mapped.split_into_chunks(50)?;
+
+        assert!(chunks.len() <= 2);
+        let total_lines: usize = chunks.iter().map(|c| c.lines().count()).sum();
+        assert_eq!(total_lines, 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_into_chunks_handles_multibyte_char_at_approx_boundary() -> Result<()> {
+        // Each line is long enough, and the device name's 'é' (2 bytes in
+        // UTF-8) is positioned so that at least one chunk's naive `i *
+        // len / n` split point lands mid-character without the
+        // char-boundary rounding.
+        let mut temp_file = NamedTempFile::new()?;
+        writeln!(temp_file, "id|device|value")?;
+        for i in 0..20 {
+            writeln!(temp_file, "{}|dév{}|{}.0", i, i, i)?;
+        }
+
+        let mapped = MappedCsvFile::new(temp_file.path())?;
+        let chunks = mapped.split_into_chunks(6)?;
+
+        let reassembled_lines: usize = chunks
+            .iter()
+            .map(|chunk| chunk.lines().filter(|l| !l.is_empty()).count())
+            .sum();
+        assert_eq!(reassembled_lines, 20);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_records_lazily_borrows_fields() -> Result<()> {
+        let mut temp_file = NamedTempFile::new()?;
+        writeln!(temp_file, "id|device|temperature|humidity")?;
+        writeln!(temp_file, "1|dev1|23.5|45.2")?;
+        writeln!(temp_file, "2|dev2|24.1|46.8")?;
+
+        let mapped = MappedCsvFile::new(temp_file.path())?;
+        let records: Vec<Record> = mapped.records('|')?.collect();
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].field(1).as_deref(), Some("dev1"));
+        assert_eq!(records[1].field(1).as_deref(), Some("dev2"));
+        assert_eq!(records[0].field(99), None);
+        assert_eq!(
+            records[0]
+                .fields()
+                .map(|f| f.into_owned())
+                .collect::<Vec<_>>(),
+            vec!["1", "dev1", "23.5", "45.2"]
+        );
+        assert_eq!(records[0].raw(), "1|dev1|23.5|45.2");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_records_quoted_handles_embedded_delimiter_and_newline() -> Result<()> {
+        let mut temp_file = NamedTempFile::new()?;
+        writeln!(temp_file, "id,device,note")?;
+        writeln!(temp_file, "1,dev1,\"hello, world\"")?;
+        writeln!(temp_file, "2,dev2,\"multi\nline\"")?;
+        writeln!(temp_file, "3,dev3,plain")?;
+
+        let mapped = MappedCsvFile::new(temp_file.path())?;
+        let records: Vec<Record> = mapped.records_quoted(',', '"')?.collect();
+
+        assert_eq!(records.len(), 3);
+
+        let row1: Vec<String> = records[0].fields().map(|f| f.into_owned()).collect();
+        assert_eq!(row1, vec!["1", "dev1", "hello, world"]);
+
+        let row2: Vec<String> = records[1].fields().map(|f| f.into_owned()).collect();
+        assert_eq!(row2, vec!["2", "dev2", "multi\nline"]);
+
+        let row3: Vec<String> = records[2].fields().map(|f| f.into_owned()).collect();
+        assert_eq!(row3, vec!["3", "dev3", "plain"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_records_quoted_collapses_doubled_quotes() -> Result<()> {
+        let mut temp_file = NamedTempFile::new()?;
+        writeln!(temp_file, "id,note")?;
+        writeln!(temp_file, "1,\"she said \"\"hi\"\"\"")?;
+
+        let mapped = MappedCsvFile::new(temp_file.path())?;
+        let records: Vec<Record> = mapped.records_quoted(',', '"')?.collect();
+
+        let fields: Vec<String> = records[0].fields().map(|f| f.into_owned()).collect();
+        assert_eq!(fields, vec!["1", "she said \"hi\""]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_records_with_progress_reports_monotonic_totals() -> Result<()> {
+        let mut temp_file = NamedTempFile::new()?;
+        writeln!(temp_file, "id|device|value")?;
+        for i in 0..5 {
+            writeln!(temp_file, "{}|dev1|{}.0", i, i)?;
+        }
+
+        let mapped = MappedCsvFile::new(temp_file.path())?;
+        let total_bytes = mapped.mmap.len() - mapped.data_start_offset;
+
+        let mut seen = Vec::new();
+        let records: Vec<Record> = mapped
+            .records_with_progress('|', |done, total| seen.push((done, total)))?
+            .collect();
+
+        assert_eq!(records.len(), 5);
+        assert_eq!(seen.len(), 5);
+        assert!(seen.iter().all(|&(_, total)| total == total_bytes));
+        assert!(seen.windows(2).all(|w| w[0].0 <= w[1].0));
+        assert_eq!(seen.last().unwrap().0, total_bytes);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_par_records_quoted_preserves_every_line_and_record_count() -> Result<()> {
+        let mut temp_file = NamedTempFile::new()?;
+        writeln!(temp_file, "id,device,note")?;
+        for i in 0..10 {
+            writeln!(temp_file, "{},dev1,plain", i)?;
+        }
+        writeln!(temp_file, "99,dev1,\"embedded\nnewline\"")?;
+
+        let mapped = MappedCsvFile::new(temp_file.path())?;
+        let chunks = mapped.par_records_quoted('"')?;
+
+        let total_records: usize = chunks
+            .iter()
+            .map(|chunk| {
+                QuotedRecordIter {
+                    remaining: chunk,
+                    delimiter: ',',
+                    quote: '"',
+                }
+                .count()
+            })
+            .sum();
+        assert_eq!(total_records, 11);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_into_chunks_quoted_never_cuts_inside_quoted_newline() -> Result<()> {
+        let mut temp_file = NamedTempFile::new()?;
+        writeln!(temp_file, "id,device,note")?;
+        for i in 0..10 {
+            writeln!(temp_file, "{},dev1,plain", i)?;
+        }
+        writeln!(temp_file, "99,dev1,\"embedded\nnewline\"")?;
+        for i in 10..20 {
+            writeln!(temp_file, "{},dev1,plain", i)?;
+        }
+
+        let mapped = MappedCsvFile::new(temp_file.path())?;
+        let chunks = mapped.split_into_chunks_quoted(6, '"')?;
+
+        // No chunk may end with an odd number of quote characters, which
+        // would mean it cut inside an open quoted field.
+        for chunk in &chunks {
+            assert_eq!(chunk.chars().filter(|&c| c == '"').count() % 2, 0);
+        }
+
+        let total_quoted_records = chunks
+            .iter()
+            .map(|chunk| chunk.matches('\n').count())
+            .sum::<usize>();
+        assert!(total_quoted_records > 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_into_chunks_quoted_handles_multibyte_char_at_approx_boundary() -> Result<()> {
+        let mut temp_file = NamedTempFile::new()?;
+        writeln!(temp_file, "id,device,note")?;
+        for i in 0..20 {
+            writeln!(temp_file, "{},dév{},plain", i, i)?;
+        }
+
+        let mapped = MappedCsvFile::new(temp_file.path())?;
+        let chunks = mapped.split_into_chunks_quoted(6, '"')?;
+
+        let reassembled_lines: usize = chunks
+            .iter()
+            .map(|chunk| chunk.lines().filter(|l| !l.is_empty()).count())
+            .sum();
+        assert_eq!(reassembled_lines, 20);
+
         Ok(())
     }
 }
@@ -0,0 +1,206 @@
+use crate::types::CsvChunk;
+use ahash::AHashMap;
+use csv::ReaderBuilder;
+
+/// Controls which columns are tallied and how each column's table is shaped.
+#[derive(Debug, Clone)]
+pub struct FrequencyConfig {
+    /// Column indices to tally; empty means all columns
+    pub select: Vec<usize>,
+    /// Cap each column's table to its top N values by count (0 = unbounded)
+    pub limit: usize,
+    /// Sort ascending by count instead of the default descending
+    pub ascending: bool,
+    /// Count empty/missing fields instead of skipping them
+    pub include_nulls: bool,
+}
+
+impl Default for FrequencyConfig {
+    fn default() -> Self {
+        Self {
+            select: Vec::new(),
+            limit: 0,
+            ascending: false,
+            include_nulls: false,
+        }
+    }
+}
+
+/// One row of a flattened frequency table
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrequencyEntry {
+    pub field: String,
+    pub value: String,
+    pub count: u64,
+}
+
+/// Tally exact `(column, value) -> count` for a single chunk. Workers call
+/// this independently; `merge_frequency_maps` reduces the partials
+/// afterwards, so no locking is needed on the hot path.
+pub fn count_chunk_frequencies(
+    chunk: &CsvChunk,
+    config: &FrequencyConfig,
+) -> AHashMap<(usize, String), u64> {
+    let mut counts = AHashMap::new();
+
+    if chunk.data.is_empty() {
+        return counts;
+    }
+
+    let mut reader = ReaderBuilder::new()
+        .delimiter(b'|')
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(chunk.data.as_bytes());
+
+    for record in reader.records().flatten() {
+        for (col, field) in record.iter().enumerate() {
+            if !config.select.is_empty() && !config.select.contains(&col) {
+                continue;
+            }
+            if field.is_empty() && !config.include_nulls {
+                continue;
+            }
+            *counts.entry((col, field.to_string())).or_insert(0) += 1;
+        }
+    }
+
+    counts
+}
+
+/// Reduce per-worker partial frequency maps into one table, summing counts
+/// for identical `(column, value)` keys across all workers.
+pub fn merge_frequency_maps(
+    partials: Vec<AHashMap<(usize, String), u64>>,
+) -> AHashMap<(usize, String), u64> {
+    let mut merged: AHashMap<(usize, String), u64> = AHashMap::new();
+    for partial in partials {
+        for (key, count) in partial {
+            *merged.entry(key).or_insert(0) += count;
+        }
+    }
+    merged
+}
+
+/// Flatten a merged frequency map into sorted, per-column-capped entries,
+/// keyed by header column name when one is available. Ties break on the
+/// value string so output is stable across runs regardless of hash-map
+/// iteration order.
+pub fn build_frequency_table(
+    merged: AHashMap<(usize, String), u64>,
+    header: &str,
+    config: &FrequencyConfig,
+) -> Vec<FrequencyEntry> {
+    let header_columns: Vec<&str> = header.split('|').collect();
+
+    let mut by_column: AHashMap<usize, Vec<(String, u64)>> = AHashMap::new();
+    for ((col, value), count) in merged {
+        by_column.entry(col).or_insert_with(Vec::new).push((value, count));
+    }
+
+    let mut columns: Vec<usize> = by_column.keys().copied().collect();
+    columns.sort_unstable();
+
+    let mut entries = Vec::new();
+    for col in columns {
+        let mut values = by_column.remove(&col).unwrap_or_default();
+
+        values.sort_by(|a, b| {
+            let by_count = if config.ascending {
+                a.1.cmp(&b.1)
+            } else {
+                b.1.cmp(&a.1)
+            };
+            by_count.then_with(|| a.0.cmp(&b.0))
+        });
+
+        if config.limit > 0 {
+            values.truncate(config.limit);
+        }
+
+        let field_name = header_columns
+            .get(col)
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| col.to_string());
+
+        for (value, count) in values {
+            entries.push(FrequencyEntry {
+                field: field_name.clone(),
+                value,
+                count,
+            });
+        }
+    }
+
+    entries
+}
+
+/// Render frequency entries as `field,value,count` CSV rows, header included.
+pub fn frequency_table_to_csv(entries: &[FrequencyEntry]) -> String {
+    let mut output = String::from("field,value,count\n");
+    for entry in entries {
+        output.push_str(&format!("{},{},{}\n", entry.field, entry.value, entry.count));
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(data: &str) -> CsvChunk {
+        CsvChunk {
+            data: data.to_string(),
+            header: "id|device|status".to_string(),
+            device_ids: vec![],
+            line_count: data.lines().count(),
+            metrics: crate::types::default_metrics(),
+        }
+    }
+
+    #[test]
+    fn test_count_and_merge_chunk_frequencies() {
+        let config = FrequencyConfig::default();
+        let a = count_chunk_frequencies(&chunk("1|dev1|ok\n2|dev1|ok"), &config);
+        let b = count_chunk_frequencies(&chunk("3|dev2|ok"), &config);
+
+        let merged = merge_frequency_maps(vec![a, b]);
+        assert_eq!(merged.get(&(2, "ok".to_string())), Some(&3));
+        assert_eq!(merged.get(&(1, "dev1".to_string())), Some(&2));
+    }
+
+    #[test]
+    fn test_build_frequency_table_respects_limit_and_order() {
+        let config = FrequencyConfig {
+            limit: 1,
+            ..Default::default()
+        };
+
+        let data = chunk("1|dev1|ok\n2|dev1|ok\n3|dev2|fail");
+        let counts = count_chunk_frequencies(&data, &FrequencyConfig::default());
+        let entries = build_frequency_table(counts, &data.header, &config);
+
+        let device_entries: Vec<&FrequencyEntry> =
+            entries.iter().filter(|e| e.field == "device").collect();
+        assert_eq!(device_entries.len(), 1);
+        assert_eq!(device_entries[0].value, "dev1");
+        assert_eq!(device_entries[0].count, 2);
+    }
+
+    #[test]
+    fn test_include_nulls_flag() {
+        let data = chunk("1|dev1|\n2||ok");
+
+        let without_nulls = count_chunk_frequencies(&data, &FrequencyConfig::default());
+        assert!(!without_nulls.contains_key(&(2, String::new())));
+        assert!(!without_nulls.contains_key(&(1, String::new())));
+
+        let config = FrequencyConfig {
+            include_nulls: true,
+            ..Default::default()
+        };
+        let with_nulls = count_chunk_frequencies(&data, &config);
+        assert_eq!(with_nulls.get(&(2, String::new())), Some(&1));
+        assert_eq!(with_nulls.get(&(1, String::new())), Some(&1));
+    }
+}
@@ -1,9 +1,13 @@
 // Re-export modules for external use
 pub mod data_analysis;
+pub mod data_conversion;
 pub mod device_hash;
 pub mod error;
 pub mod file_mapping;
+pub mod frequency;
 pub mod parallel_processor;
+pub mod resource_monitor;
+pub mod stream_processor;
 pub mod types;
 
 pub use data_analysis::analyze_csv_chunk;
@@ -49,12 +53,12 @@ fn test_end_to_end_csv_processing() -> anyhow::Result<()> {
     assert!(hash_table.contains_key("dev2"));
     
     // Test partitioning
-    let chunks = partition_by_device(&hash_table, 2, &mapped_file.header);
+    let chunks = partition_by_device(&hash_table, 2, &mapped_file.header, &crate::types::default_metrics());
     assert!(!chunks.is_empty());
     assert!(chunks.len() <= 2);
     
     // Test parallel processing
-    let results = process_chunks_parallel(chunks)?;
+    let results = process_chunks_parallel(chunks, None)?;
     assert!(!results.is_empty());
     
     // Verify we have some aggregations
@@ -71,6 +75,7 @@ fn test_csv_chunk_analysis() -> anyhow::Result<()> {
         header: "id|device|data|temperatura|umidade|luminosidade|ruido|eco2|etvoc".to_string(),
         device_ids: vec!["dev1".to_string()],
         line_count: 2,
+        metrics: crate::types::default_metrics(),
     };
     
     let result = analyze_csv_chunk(&chunk)?;
@@ -99,7 +104,7 @@ fn test_device_partitioning_load_balance() {
     hash_table.insert("dev2".to_string(), vec!["line2".to_string(); 50]);
     hash_table.insert("dev3".to_string(), vec!["line3".to_string(); 25]);
     
-    let chunks = partition_by_device(&hash_table, 3, "header");
+    let chunks = partition_by_device(&hash_table, 3, "header", &crate::types::default_metrics());
     
     // Should create chunks that attempt to balance load
     assert_eq!(chunks.len(), 3);
@@ -117,6 +122,7 @@ fn test_empty_data_handling() -> anyhow::Result<()> {
         header: "id|device|data|temperatura".to_string(),
         device_ids: vec![],
         line_count: 0,
+        metrics: crate::types::default_metrics(),
     };
     
     let result = analyze_csv_chunk(&chunk)?;
@@ -1,21 +1,48 @@
 mod data_analysis;
+mod data_conversion;
 mod device_hash;
 mod error;
 mod file_mapping;
+mod frequency;
 mod parallel_processor;
+mod resource_monitor;
+mod stream_processor;
 mod types;
 
 use anyhow::{Context, Result};
 use clap::Parser;
+use rayon::prelude::*;
+use std::collections::HashSet;
 use std::fs::File;
 use std::io::Write;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
-use crate::data_analysis::results_to_csv;
-use crate::device_hash::{build_device_hash_table, calculate_load_balance_stats, partition_by_device};
+use crate::data_analysis::{analyze_parquet_file, results_to_csv, results_to_json, results_to_parquet};
+use crate::data_conversion::{write_aggregations_csv, write_aggregations_json};
+use crate::device_hash::{
+    build_device_hash_table, calculate_load_balance_stats, partition_by_device, partition_by_device_index,
+};
 use crate::file_mapping::MappedCsvFile;
+use crate::frequency::{
+    build_frequency_table, count_chunk_frequencies, frequency_table_to_csv, merge_frequency_maps,
+    FrequencyConfig,
+};
 use crate::parallel_processor::{print_processing_stats, process_chunks_parallel, WorkStealingProcessor};
-use crate::types::{ProcessingConfig, ProcessingStats};
+use crate::resource_monitor::{new_progress_counters, print_monitor_summary, ResourceMonitor};
+use crate::stream_processor::process_stream;
+use crate::types::{parse_metrics, parse_row_filter, InputFormat, OutputFormat, ProcessingConfig, ProcessingStats};
+
+// The rayon/work-stealing paths allocate and free large per-chunk buffers
+// repeatedly; jemalloc's aggressive dirty-page decay returns that memory to
+// the OS faster than the system allocator's default behavior.
+#[cfg(feature = "jemalloc")]
+#[global_allocator]
+static GLOBAL: tikv_jemallocator::Jemalloc = tikv_jemallocator::Jemalloc;
+
+#[cfg(feature = "jemalloc")]
+#[allow(non_upper_case_globals)]
+#[export_name = "malloc_conf"]
+pub static malloc_conf: &[u8] = b"dirty_decay_ms:500,muzzy_decay_ms:-1\0";
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -46,6 +73,98 @@ struct Args {
     /// Output file path (default: "result.csv")
     #[arg(short, long, default_value = "result.csv")]
     output: String,
+
+    /// Input/output format: "csv" (mmap + hash-table pipeline) or "parquet" (Polars lazy scan)
+    #[arg(long, value_enum, default_value_t = InputFormat::Csv)]
+    format: InputFormat,
+
+    /// Comma-separated aggregation metrics to compute per sensor (e.g. "max,mean,min,std,median,p95")
+    #[arg(long, default_value = "max,mean,min")]
+    metrics: String,
+
+    /// Sample CPU/memory/throughput in the background while processing runs
+    #[arg(long)]
+    monitor: bool,
+
+    /// Monitor sampling interval in milliseconds
+    #[arg(long, default_value_t = 500)]
+    monitor_interval_ms: u64,
+
+    /// How the final results and run statistics are reported to stdout, after the phase progress lines: "text" (performance summary, default) or "json"
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    output_format: OutputFormat,
+
+    /// Also write the aggregations + ProcessingStats as JSON to this path, independent of --output-format
+    #[arg(long)]
+    stats_json: Option<String>,
+
+    /// Compute per-column value-frequency tables alongside the aggregation pipeline
+    #[arg(long)]
+    frequency: bool,
+
+    /// Comma-separated column indices to tally (default: all columns)
+    #[arg(long)]
+    frequency_columns: Option<String>,
+
+    /// Cap each column's frequency table to its top N values by count (0 = unbounded)
+    #[arg(long, default_value_t = 0)]
+    frequency_limit: usize,
+
+    /// Sort frequency tables ascending by count instead of the default descending
+    #[arg(long)]
+    frequency_ascending: bool,
+
+    /// Count empty/missing fields in frequency tables instead of skipping them
+    #[arg(long)]
+    frequency_include_nulls: bool,
+
+    /// Output path for the frequency table CSV
+    #[arg(long, default_value = "frequency.csv")]
+    frequency_output: String,
+
+    /// Also write the deduplicated device/year-month/sensor aggregation table as CSV to this path
+    #[arg(long)]
+    export_csv: Option<String>,
+
+    /// Also write the deduplicated device/year-month/sensor aggregation table as JSON to this path
+    #[arg(long)]
+    export_json: Option<String>,
+
+    /// Stream the file in fixed-size windows instead of building the full device hash table,
+    /// for inputs too large to comfortably fit in RAM
+    #[arg(long)]
+    streaming: bool,
+
+    /// Only aggregate rows matching this predicate, e.g. "device~sensor", "temperatura>30", or "device=~^dev[0-9]+$"
+    #[arg(long)]
+    filter: Option<String>,
+
+    /// Replace the device hash table and partitioning phases with
+    /// MappedCsvFile's newline-aligned, adaptively-sized byte chunking
+    /// (par_records/par_records_quoted), reporting scan progress via
+    /// records_with_progress when --quote is not given
+    #[arg(long)]
+    parallel_scan: bool,
+
+    /// RFC 4180 quote character for --parallel-scan (e.g. '"'); only takes
+    /// effect together with --parallel-scan
+    #[arg(long)]
+    quote: Option<char>,
+
+    /// Partition by device from a persisted `<file>.device_index.json` byte
+    /// offset index instead of rebuilding the device hash table every run,
+    /// building and writing that sidecar on a first run
+    #[arg(long)]
+    use_device_index: bool,
+}
+
+/// Name of the allocator backing this binary, for self-documenting benchmark runs.
+fn active_allocator() -> &'static str {
+    if cfg!(feature = "jemalloc") {
+        "jemalloc (dirty_decay_ms:500, muzzy_decay_ms:-1)"
+    } else {
+        "system default"
+    }
 }
 
 fn main() -> Result<()> {
@@ -55,12 +174,20 @@ fn main() -> Result<()> {
     println!("===============================================");
     
     let total_start = Instant::now();
-    
+
+    let metrics = parse_metrics(&args.metrics).map_err(|e| anyhow::anyhow!(e))?;
+    println!("[SYSTEM] Metrics: {}", args.metrics);
+    println!("[SYSTEM] Allocator: {}", active_allocator());
+
+    if args.format == InputFormat::Parquet {
+        return run_parquet_pipeline(&args, total_start, &metrics);
+    }
+
     // Validate file extension
     if !MappedCsvFile::validate_csv_extension(&args.file_path) {
         return Err(anyhow::anyhow!("Invalid file extension. Expected .csv file"));
     }
-    
+
     // Print system information
     let num_processors = num_cpus::get();
     let num_workers = args.num_workers.unwrap_or(num_processors);
@@ -74,11 +201,25 @@ fn main() -> Result<()> {
     );
     
     // Create processing configuration
+    let row_filter = args
+        .filter
+        .as_deref()
+        .map(parse_row_filter)
+        .transpose()
+        .map_err(|e| anyhow::anyhow!(e))?;
+
+    if let Some(filter) = &row_filter {
+        println!("[SYSTEM] Row filter: {} on column '{}'", args.filter.as_deref().unwrap(), filter.column_name());
+    }
+
     let config = ProcessingConfig {
         file_path: args.file_path.clone(),
         device_column: args.device_column.clone(),
         num_workers,
         delimiter: args.delimiter,
+        row_filter,
+        monitor_enabled: args.monitor,
+        monitor_interval_ms: args.monitor_interval_ms,
     };
     
     // Phase 1: Memory mapping
@@ -96,47 +237,110 @@ fn main() -> Result<()> {
         .find_column_index(&args.device_column, args.delimiter)
         .ok_or_else(|| anyhow::anyhow!("Device column '{}' not found", args.device_column))?;
     
-    println!("[PHASE 1] Device column '{}' found at index {}", 
+    println!("[PHASE 1] Device column '{}' found at index {}",
         args.device_column, device_column_index);
-    
-    // Phase 2: Build device hash table
-    println!("\n[PHASE 2] Building device hash table...");
-    let hash_start = Instant::now();
-    
-    let data = mapped_file.get_data()
-        .with_context(|| "Failed to get CSV data")?;
-    
-    let device_hash_table = build_device_hash_table(
-        data,
-        device_column_index,
-        &mapped_file.header,
-        &config,
-    ).with_context(|| "Failed to build device hash table")?;
-    
-    let hash_time = hash_start.elapsed();
-    
-    // Count total lines
-    let total_lines: usize = device_hash_table.iter().map(|entry| entry.value().len()).sum();
-    let unique_devices = device_hash_table.len();
-    
-    println!("[PHASE 2] ✅ Hash table built in {:.2}ms", hash_time.as_millis());
-    println!("[PHASE 2] Total data lines: {}", total_lines);
-    println!("[PHASE 2] Unique devices: {}", unique_devices);
-    
-    if unique_devices == 0 {
-        return Err(anyhow::anyhow!("No devices found in the dataset"));
+
+    if args.streaming {
+        return run_streaming_pipeline(&args, &mapped_file, &config, &metrics, total_start, mapping_time);
     }
-    
-    // Phase 3: Partition data by device
-    println!("\n[PHASE 3] Partitioning data by device...");
-    let partition_start = Instant::now();
-    
-    let chunks = partition_by_device(&device_hash_table, num_workers, &mapped_file.header);
-    let partition_time = partition_start.elapsed();
-    
-    println!("[PHASE 3] ✅ Data partitioned in {:.2}ms", partition_time.as_millis());
-    println!("[PHASE 3] Created {} chunks", chunks.len());
-    
+
+    if args.parallel_scan {
+        return run_parallel_scan_pipeline(
+            &args,
+            &mapped_file,
+            &config,
+            &metrics,
+            num_workers,
+            total_start,
+            mapping_time,
+        );
+    }
+
+    let progress = new_progress_counters();
+
+    // Phases 2-3: either build the device hash table and partition from it
+    // fresh every run, or, with `--use-device-index`, load a persisted
+    // byte-offset `DeviceIndex` sidecar (building and writing it on a first
+    // run) and partition straight from that, skipping the full-file scan on
+    // every repeat run over the same file.
+    let (chunks, total_lines, unique_devices, hash_time, partition_time) = if args.use_device_index {
+        println!("\n[PHASE 2] Loading or building persisted device index...");
+        let hash_start = Instant::now();
+
+        let sidecar_path = std::path::PathBuf::from(format!("{}.device_index.json", args.file_path));
+        let index = mapped_file
+            .load_or_build_device_index(&sidecar_path, device_column_index, args.delimiter)
+            .with_context(|| "Failed to load or build device index")?;
+
+        let hash_time = hash_start.elapsed();
+        let unique_devices = index.devices().count();
+        let total_lines: usize = index.devices().map(|device_id| index.line_count(device_id)).sum();
+
+        println!(
+            "[PHASE 2] ✅ Device index ready in {:.2}ms ('{}')",
+            hash_time.as_millis(),
+            sidecar_path.display()
+        );
+        println!("[PHASE 2] Total data lines: {}", total_lines);
+        println!("[PHASE 2] Unique devices: {}", unique_devices);
+
+        if unique_devices == 0 {
+            return Err(anyhow::anyhow!("No devices found in the dataset"));
+        }
+
+        println!("\n[PHASE 3] Partitioning data by device (from index)...");
+        let partition_start = Instant::now();
+
+        let chunks =
+            partition_by_device_index(&index, &mapped_file, num_workers, &mapped_file.header, &metrics);
+        let partition_time = partition_start.elapsed();
+
+        println!("[PHASE 3] ✅ Data partitioned in {:.2}ms", partition_time.as_millis());
+        println!("[PHASE 3] Created {} chunks", chunks.len());
+
+        (chunks, total_lines, unique_devices, hash_time, partition_time)
+    } else {
+        // Phase 2: Build device hash table
+        println!("\n[PHASE 2] Building device hash table...");
+        let hash_start = Instant::now();
+
+        let data = mapped_file.get_data()
+            .with_context(|| "Failed to get CSV data")?;
+
+        let device_hash_table = build_device_hash_table(
+            data,
+            device_column_index,
+            &mapped_file.header,
+            &config,
+        ).with_context(|| "Failed to build device hash table")?;
+
+        let hash_time = hash_start.elapsed();
+
+        // Count total lines
+        let total_lines: usize = device_hash_table.iter().map(|entry| entry.value().len()).sum();
+        let unique_devices = device_hash_table.len();
+
+        println!("[PHASE 2] ✅ Hash table built in {:.2}ms", hash_time.as_millis());
+        println!("[PHASE 2] Total data lines: {}", total_lines);
+        println!("[PHASE 2] Unique devices: {}", unique_devices);
+
+        if unique_devices == 0 {
+            return Err(anyhow::anyhow!("No devices found in the dataset"));
+        }
+
+        // Phase 3: Partition data by device
+        println!("\n[PHASE 3] Partitioning data by device...");
+        let partition_start = Instant::now();
+
+        let chunks = partition_by_device(&device_hash_table, num_workers, &mapped_file.header, &metrics);
+        let partition_time = partition_start.elapsed();
+
+        println!("[PHASE 3] ✅ Data partitioned in {:.2}ms", partition_time.as_millis());
+        println!("[PHASE 3] Created {} chunks", chunks.len());
+
+        (chunks, total_lines, unique_devices, hash_time, partition_time)
+    };
+
     // Print load balancing statistics
     let (imbalance_ratio, min_load, max_load) = calculate_load_balance_stats(&chunks);
     println!("[PHASE 3] Load balance - Min: {} lines, Max: {} lines, Imbalance ratio: {:.2}", 
@@ -145,7 +349,66 @@ fn main() -> Result<()> {
     if chunks.is_empty() {
         return Err(anyhow::anyhow!("No data chunks created"));
     }
-    
+
+    let chunks_created = chunks.len();
+
+    // Optional resource monitor: samples CPU/memory/throughput plus
+    // chunks-completed-vs-remaining in the background through the optional
+    // Phase 3b and Phase 4, printing each sample as it's taken.
+    let monitor = if config.monitor_enabled {
+        println!(
+            "[SYSTEM] Resource monitor enabled (interval: {}ms)",
+            config.monitor_interval_ms
+        );
+        Some(ResourceMonitor::start(
+            progress.clone(),
+            chunks_created,
+            Duration::from_millis(config.monitor_interval_ms),
+        ))
+    } else {
+        None
+    };
+
+    // Optional: per-column value-frequency tables, reusing the same chunks
+    // the aggregation pipeline is about to consume.
+    let frequency_table = if args.frequency {
+        println!("\n[PHASE 3b] Computing per-column frequency tables...");
+        let freq_start = Instant::now();
+
+        let select: Vec<usize> = match &args.frequency_columns {
+            Some(spec) => spec
+                .split(',')
+                .map(|s| s.trim().parse::<usize>())
+                .collect::<std::result::Result<Vec<usize>, _>>()
+                .with_context(|| format!("Invalid --frequency-columns value: {}", spec))?,
+            None => Vec::new(),
+        };
+
+        let frequency_config = FrequencyConfig {
+            select,
+            limit: args.frequency_limit,
+            ascending: args.frequency_ascending,
+            include_nulls: args.frequency_include_nulls,
+        };
+
+        let partials = chunks
+            .par_iter()
+            .map(|chunk| count_chunk_frequencies(chunk, &frequency_config))
+            .collect();
+        let merged = merge_frequency_maps(partials);
+        let table = build_frequency_table(merged, &mapped_file.header, &frequency_config);
+
+        println!(
+            "[PHASE 3b] ✅ Frequency tables computed in {:.2}ms ({} rows)",
+            freq_start.elapsed().as_millis(),
+            table.len()
+        );
+
+        Some(table)
+    } else {
+        None
+    };
+
     // Phase 4: Parallel processing
     println!("\n[PHASE 4] Processing data chunks in parallel...");
     let processing_start = Instant::now();
@@ -154,30 +417,39 @@ fn main() -> Result<()> {
         // Use async processing
         let rt = tokio::runtime::Runtime::new()
             .with_context(|| "Failed to create async runtime")?;
-        rt.block_on(crate::parallel_processor::process_chunks_async(chunks))
+        rt.block_on(crate::parallel_processor::process_chunks_async(
+            chunks,
+            Some(progress.clone()),
+        ))
             .with_context(|| "Async processing failed")?
     } else if args.use_work_stealing {
         // Use work-stealing processor
         let processor = WorkStealingProcessor::new(num_workers);
-        processor.process_chunks(chunks)
+        processor.process_chunks(chunks, Some(progress.clone()))
             .with_context(|| "Work-stealing processing failed")?
     } else {
         // Use rayon parallel processing (default)
-        process_chunks_parallel(chunks)
+        process_chunks_parallel(chunks, Some(progress.clone()))
             .with_context(|| "Parallel processing failed")?
     };
-    
+
     let processing_time = processing_start.elapsed();
     println!("[PHASE 4] ✅ All chunks processed in {:.2}s", processing_time.as_secs_f64());
-    
+
     // Print detailed processing statistics
     print_processing_stats(&results);
+
+    // Stop the monitor now that Phase 4 is done and print its summary
+    if let Some(monitor) = monitor {
+        let samples = monitor.stop();
+        print_monitor_summary(&samples);
+    }
     
     // Phase 5: Generate output
     println!("[PHASE 5] Writing results to file...");
     let output_start = Instant::now();
     
-    let csv_output = results_to_csv(&results);
+    let csv_output = results_to_csv(&results, &metrics);
     
     let mut output_file = File::create(&args.output)
         .with_context(|| format!("Failed to create output file: {}", args.output))?;
@@ -187,36 +459,571 @@ fn main() -> Result<()> {
     
     let output_time = output_start.elapsed();
     println!("[PHASE 5] ✅ Results written to '{}' in {:.2}ms", args.output, output_time.as_millis());
-    
+
+    if let Some(table) = &frequency_table {
+        let frequency_csv = frequency_table_to_csv(table);
+        std::fs::write(&args.frequency_output, &frequency_csv).with_context(|| {
+            format!("Failed to write frequency output file: {}", args.frequency_output)
+        })?;
+        println!(
+            "[PHASE 5] Frequency table written to '{}' ({} rows)",
+            args.frequency_output,
+            table.len()
+        );
+    }
+
+    if let Some(export_csv_path) = &args.export_csv {
+        write_aggregations_csv(&results, export_csv_path, &config, &metrics)?;
+        println!("[PHASE 5] Deduplicated aggregations exported to '{}'", export_csv_path);
+    }
+
+    if let Some(export_json_path) = &args.export_json {
+        write_aggregations_json(&results, export_json_path, &config, &metrics)?;
+        println!("[PHASE 5] Deduplicated aggregations exported to '{}'", export_json_path);
+    }
+
     // Calculate total aggregations
     let total_aggregations: usize = results.iter().map(|r| r.aggregations.len()).sum();
     println!("[PHASE 5] Total aggregations written: {}", total_aggregations);
     
     let total_time = total_start.elapsed();
-    
-    // Final performance summary
-    println!("\n🏁 PERFORMANCE SUMMARY 🏁");
-    println!("========================");
-    println!("File mapping:           {:.2}ms", mapping_time.as_millis());
-    println!("Hash table building:    {:.2}ms", hash_time.as_millis());
-    println!("Data partitioning:      {:.2}ms", partition_time.as_millis());
-    println!("Parallel processing:    {:.2}ms", processing_time.as_millis());
-    println!("Output writing:         {:.2}ms", output_time.as_millis());
-    println!("------------------------");
-    println!("TOTAL EXECUTION TIME:   {:.2}s", total_time.as_secs_f64());
-    
-    // Throughput calculations
-    if total_time.as_secs_f64() > 0.0 {
-        let lines_per_second = total_lines as f64 / total_time.as_secs_f64();
-        let aggregations_per_second = total_aggregations as f64 / total_time.as_secs_f64();
-        
-        println!("------------------------");
-        println!("Processing throughput:  {:.2} lines/second", lines_per_second);
-        println!("Aggregation rate:       {:.2} aggregations/second", aggregations_per_second);
-    }
-    
-    println!("========================");
-    println!("🎉 Processing completed successfully! 🎉");
-    
+
+    let throughput_lines_per_sec = if total_time.as_secs_f64() > 0.0 {
+        total_lines as f64 / total_time.as_secs_f64()
+    } else {
+        0.0
+    };
+
+    let stats = ProcessingStats {
+        total_lines,
+        unique_devices,
+        chunks_created,
+        mapping_time_ms: mapping_time.as_millis() as f64,
+        hash_building_time_ms: hash_time.as_millis() as f64,
+        partitioning_time_ms: partition_time.as_millis() as f64,
+        processing_time_ms: processing_time.as_millis() as f64,
+        output_time_ms: output_time.as_millis() as f64,
+        total_time_ms: total_time.as_millis() as f64,
+        load_balance_imbalance_ratio: imbalance_ratio,
+        throughput_lines_per_sec,
+    };
+
+    if let Some(stats_json_path) = &args.stats_json {
+        let json = results_to_json(&results, &stats)?;
+        std::fs::write(stats_json_path, &json)
+            .with_context(|| format!("Failed to write stats JSON file: {}", stats_json_path))?;
+        println!("[SYSTEM] Stats + aggregations written to '{}'", stats_json_path);
+    }
+
+    match args.output_format {
+        OutputFormat::Json => {
+            println!("{}", results_to_json(&results, &stats)?);
+        }
+        OutputFormat::Text => {
+            // Final performance summary
+            println!("\n🏁 PERFORMANCE SUMMARY 🏁");
+            println!("========================");
+            println!("File mapping:           {:.2}ms", mapping_time.as_millis());
+            println!("Hash table building:    {:.2}ms", hash_time.as_millis());
+            println!("Data partitioning:      {:.2}ms", partition_time.as_millis());
+            println!("Parallel processing:    {:.2}ms", processing_time.as_millis());
+            println!("Output writing:         {:.2}ms", output_time.as_millis());
+            println!("------------------------");
+            println!("TOTAL EXECUTION TIME:   {:.2}s", total_time.as_secs_f64());
+
+            // Throughput calculations
+            if total_time.as_secs_f64() > 0.0 {
+                let aggregations_per_second = total_aggregations as f64 / total_time.as_secs_f64();
+
+                println!("------------------------");
+                println!("Processing throughput:  {:.2} lines/second", throughput_lines_per_sec);
+                println!("Aggregation rate:       {:.2} aggregations/second", aggregations_per_second);
+            }
+
+            println!("========================");
+            println!("🎉 Processing completed successfully! 🎉");
+        }
+    }
+
+    Ok(())
+}
+
+/// Count distinct devices directly from the aggregated results, for pipelines
+/// that never build a device hash table or index to read the count from.
+fn unique_devices_from_results(results: &[crate::types::AnalysisResults]) -> usize {
+    results
+        .iter()
+        .flat_map(|result| result.aggregations.iter())
+        .map(|agg| agg.device.as_str())
+        .collect::<HashSet<_>>()
+        .len()
+}
+
+/// Streaming pipeline: skips the device hash table and chunk partitioning
+/// entirely, folding running statistics for the requested metrics directly
+/// off the mapped file in fixed-size windows so peak memory stays
+/// proportional to device x month x sensor cardinality instead of file size,
+/// except when median or quantile metrics are requested, which keep every value.
+fn run_streaming_pipeline(
+    args: &Args,
+    mapped_file: &MappedCsvFile,
+    config: &ProcessingConfig,
+    metrics: &[crate::types::MetricSpec],
+    total_start: Instant,
+    mapping_time: std::time::Duration,
+) -> Result<()> {
+    if args.frequency {
+        return Err(anyhow::anyhow!(
+            "--frequency is not supported together with --streaming (streaming never materializes whole chunks to tally); rerun without one of the two"
+        ));
+    }
+
+    println!("[SYSTEM] Processing mode: Streaming (bounded-memory windowed fold)");
+
+    println!("\n[PHASE 2] Folding statistics over windowed reads...");
+    let processing_start = Instant::now();
+
+    let result =
+        process_stream(mapped_file, config, metrics).with_context(|| "Streaming processing failed")?;
+
+    let processing_time = processing_start.elapsed();
+    println!("[PHASE 2] ✅ Folded {} lines in {:.2}ms", result.total_lines_processed, processing_time.as_millis());
+
+    let total_lines = result.total_lines_processed;
+    let unique_devices = unique_devices_from_results(std::slice::from_ref(&result));
+    let results = vec![result];
+    print_processing_stats(&results);
+
+    println!("[PHASE 3] Writing results to file...");
+    let output_start = Instant::now();
+
+    let csv_output = results_to_csv(&results, metrics);
+    let mut output_file = File::create(&args.output)
+        .with_context(|| format!("Failed to create output file: {}", args.output))?;
+    output_file
+        .write_all(csv_output.as_bytes())
+        .with_context(|| "Failed to write CSV output")?;
+
+    let output_time = output_start.elapsed();
+    println!("[PHASE 3] ✅ Results written to '{}' in {:.2}ms", args.output, output_time.as_millis());
+
+    if let Some(export_csv_path) = &args.export_csv {
+        write_aggregations_csv(&results, export_csv_path, config, metrics)?;
+        println!("[PHASE 3] Deduplicated aggregations exported to '{}'", export_csv_path);
+    }
+
+    if let Some(export_json_path) = &args.export_json {
+        write_aggregations_json(&results, export_json_path, config, metrics)?;
+        println!("[PHASE 3] Deduplicated aggregations exported to '{}'", export_json_path);
+    }
+
+    let total_aggregations: usize = results.iter().map(|r| r.aggregations.len()).sum();
+    let total_time = total_start.elapsed();
+
+    let throughput_lines_per_sec = if total_time.as_secs_f64() > 0.0 {
+        total_lines as f64 / total_time.as_secs_f64()
+    } else {
+        0.0
+    };
+
+    let stats = ProcessingStats {
+        total_lines,
+        unique_devices,
+        chunks_created: 0,
+        mapping_time_ms: mapping_time.as_millis() as f64,
+        hash_building_time_ms: 0.0,
+        partitioning_time_ms: 0.0,
+        processing_time_ms: processing_time.as_millis() as f64,
+        output_time_ms: output_time.as_millis() as f64,
+        total_time_ms: total_time.as_millis() as f64,
+        load_balance_imbalance_ratio: 0.0,
+        throughput_lines_per_sec,
+    };
+
+    if let Some(stats_json_path) = &args.stats_json {
+        let json = results_to_json(&results, &stats)?;
+        std::fs::write(stats_json_path, &json)
+            .with_context(|| format!("Failed to write stats JSON file: {}", stats_json_path))?;
+        println!("[SYSTEM] Stats + aggregations written to '{}'", stats_json_path);
+    }
+
+    match args.output_format {
+        OutputFormat::Json => {
+            println!("{}", results_to_json(&results, &stats)?);
+        }
+        OutputFormat::Text => {
+            println!("\n🏁 PERFORMANCE SUMMARY 🏁");
+            println!("========================");
+            println!("File mapping:           {:.2}ms", mapping_time.as_millis());
+            println!("Windowed fold:          {:.2}ms", processing_time.as_millis());
+            println!("Output writing:         {:.2}ms", output_time.as_millis());
+            println!("------------------------");
+            println!("TOTAL EXECUTION TIME:   {:.2}s", total_time.as_secs_f64());
+            println!("Total aggregations written: {}", total_aggregations);
+            println!("========================");
+            println!("🎉 Processing completed successfully! 🎉");
+        }
+    }
+
+    Ok(())
+}
+
+/// Parallel-scan pipeline: replaces the device hash table and
+/// `partition_by_device` step with `MappedCsvFile`'s newline-aligned,
+/// adaptively-sized byte chunking (`par_records`/`par_records_quoted`, sized
+/// by `adaptive_chunk_plan` rather than `--num-workers`), then feeds those
+/// chunks through the same Phase 4 parallel processing as the default
+/// pipeline. Without `--quote`, the scan pass that counts total lines reports
+/// byte progress via `records_with_progress` as it walks the file; with
+/// `--quote`, it counts `records_quoted` and reports no progress.
+fn run_parallel_scan_pipeline(
+    args: &Args,
+    mapped_file: &MappedCsvFile,
+    config: &ProcessingConfig,
+    metrics: &[crate::types::MetricSpec],
+    num_workers: usize,
+    total_start: Instant,
+    mapping_time: std::time::Duration,
+) -> Result<()> {
+    println!("[SYSTEM] Processing mode: Parallel scan (adaptive byte-range chunking)");
+
+    println!("\n[PHASE 2] Scanning records...");
+    let scan_start = Instant::now();
+
+    let total_lines = match args.quote {
+        Some(quote) => mapped_file.records_quoted(config.delimiter, quote)?.count(),
+        None => {
+            let mut last_reported_pct: u64 = 0;
+            let mut lines = 0usize;
+            for _record in mapped_file.records_with_progress(config.delimiter, |done, total| {
+                let pct = if total == 0 { 100 } else { (done as u64 * 100) / total as u64 };
+                if pct >= last_reported_pct + 10 {
+                    println!("[PHASE 2] Scanned {}% ({} / {} bytes)", pct, done, total);
+                    last_reported_pct = pct;
+                }
+            })? {
+                lines += 1;
+            }
+            lines
+        }
+    };
+
+    let scan_time = scan_start.elapsed();
+    println!("[PHASE 2] ✅ Scanned {} lines in {:.2}ms", total_lines, scan_time.as_millis());
+
+    println!("\n[PHASE 3] Splitting file into adaptively-sized byte chunks...");
+    let chunk_start = Instant::now();
+
+    let data_chunks = match args.quote {
+        Some(quote) => mapped_file
+            .par_records_quoted(quote)
+            .with_context(|| "Failed to split file into quote-aware parallel chunks")?,
+        None => mapped_file
+            .par_records()
+            .with_context(|| "Failed to split file into parallel chunks")?,
+    };
+
+    let chunks: Vec<crate::types::CsvChunk> = data_chunks
+        .into_iter()
+        .map(|data| crate::types::CsvChunk {
+            line_count: data.lines().filter(|line| !line.is_empty()).count(),
+            data: data.to_string(),
+            header: mapped_file.header.clone(),
+            device_ids: Vec::new(),
+            metrics: metrics.to_vec(),
+        })
+        .collect();
+
+    let chunk_time = chunk_start.elapsed();
+    println!("[PHASE 3] ✅ Created {} chunks in {:.2}ms", chunks.len(), chunk_time.as_millis());
+
+    if chunks.is_empty() {
+        return Err(anyhow::anyhow!("No data chunks created"));
+    }
+
+    let (imbalance_ratio, min_load, max_load) = calculate_load_balance_stats(&chunks);
+    println!(
+        "[PHASE 3] Load balance - Min: {} lines, Max: {} lines, Imbalance ratio: {:.2}",
+        min_load, max_load, imbalance_ratio
+    );
+
+    // Optional: per-column value-frequency tables, reusing the same chunks
+    // the aggregation pipeline is about to consume (mirrors the default
+    // pipeline's Phase 3b).
+    let frequency_table = if args.frequency {
+        println!("\n[PHASE 3b] Computing per-column frequency tables...");
+        let freq_start = Instant::now();
+
+        let select: Vec<usize> = match &args.frequency_columns {
+            Some(spec) => spec
+                .split(',')
+                .map(|s| s.trim().parse::<usize>())
+                .collect::<std::result::Result<Vec<usize>, _>>()
+                .with_context(|| format!("Invalid --frequency-columns value: {}", spec))?,
+            None => Vec::new(),
+        };
+
+        let frequency_config = FrequencyConfig {
+            select,
+            limit: args.frequency_limit,
+            ascending: args.frequency_ascending,
+            include_nulls: args.frequency_include_nulls,
+        };
+
+        let partials = chunks
+            .par_iter()
+            .map(|chunk| count_chunk_frequencies(chunk, &frequency_config))
+            .collect();
+        let merged = merge_frequency_maps(partials);
+        let table = build_frequency_table(merged, &mapped_file.header, &frequency_config);
+
+        println!(
+            "[PHASE 3b] ✅ Frequency tables computed in {:.2}ms ({} rows)",
+            freq_start.elapsed().as_millis(),
+            table.len()
+        );
+
+        Some(table)
+    } else {
+        None
+    };
+
+    let chunks_created = chunks.len();
+    let progress = new_progress_counters();
+
+    let monitor = if config.monitor_enabled {
+        println!(
+            "[SYSTEM] Resource monitor enabled (interval: {}ms)",
+            config.monitor_interval_ms
+        );
+        Some(ResourceMonitor::start(
+            progress.clone(),
+            chunks_created,
+            Duration::from_millis(config.monitor_interval_ms),
+        ))
+    } else {
+        None
+    };
+
+    println!("\n[PHASE 4] Processing data chunks in parallel...");
+    let processing_start = Instant::now();
+
+    let results = if args.use_async {
+        let rt = tokio::runtime::Runtime::new().with_context(|| "Failed to create async runtime")?;
+        rt.block_on(crate::parallel_processor::process_chunks_async(
+            chunks,
+            Some(progress.clone()),
+        ))
+        .with_context(|| "Async processing failed")?
+    } else if args.use_work_stealing {
+        let processor = WorkStealingProcessor::new(num_workers);
+        processor
+            .process_chunks(chunks, Some(progress.clone()))
+            .with_context(|| "Work-stealing processing failed")?
+    } else {
+        process_chunks_parallel(chunks, Some(progress.clone())).with_context(|| "Parallel processing failed")?
+    };
+
+    let processing_time = processing_start.elapsed();
+    println!("[PHASE 4] ✅ All chunks processed in {:.2}s", processing_time.as_secs_f64());
+
+    print_processing_stats(&results);
+
+    if let Some(monitor) = monitor {
+        let samples = monitor.stop();
+        print_monitor_summary(&samples);
+    }
+
+    println!("[PHASE 5] Writing results to file...");
+    let output_start = Instant::now();
+
+    let csv_output = results_to_csv(&results, metrics);
+    let mut output_file = File::create(&args.output)
+        .with_context(|| format!("Failed to create output file: {}", args.output))?;
+    output_file
+        .write_all(csv_output.as_bytes())
+        .with_context(|| "Failed to write CSV output")?;
+
+    let output_time = output_start.elapsed();
+    println!("[PHASE 5] ✅ Results written to '{}' in {:.2}ms", args.output, output_time.as_millis());
+
+    if let Some(table) = &frequency_table {
+        let frequency_csv = frequency_table_to_csv(table);
+        std::fs::write(&args.frequency_output, &frequency_csv).with_context(|| {
+            format!("Failed to write frequency output file: {}", args.frequency_output)
+        })?;
+        println!(
+            "[PHASE 5] Frequency table written to '{}' ({} rows)",
+            args.frequency_output,
+            table.len()
+        );
+    }
+
+    if let Some(export_csv_path) = &args.export_csv {
+        write_aggregations_csv(&results, export_csv_path, config, metrics)?;
+        println!("[PHASE 5] Deduplicated aggregations exported to '{}'", export_csv_path);
+    }
+
+    if let Some(export_json_path) = &args.export_json {
+        write_aggregations_json(&results, export_json_path, config, metrics)?;
+        println!("[PHASE 5] Deduplicated aggregations exported to '{}'", export_json_path);
+    }
+
+    let total_aggregations: usize = results.iter().map(|r| r.aggregations.len()).sum();
+    let total_time = total_start.elapsed();
+
+    let unique_devices = unique_devices_from_results(&results);
+    let throughput_lines_per_sec = if total_time.as_secs_f64() > 0.0 {
+        total_lines as f64 / total_time.as_secs_f64()
+    } else {
+        0.0
+    };
+
+    let stats = ProcessingStats {
+        total_lines,
+        unique_devices,
+        chunks_created,
+        mapping_time_ms: mapping_time.as_millis() as f64,
+        hash_building_time_ms: scan_time.as_millis() as f64,
+        partitioning_time_ms: chunk_time.as_millis() as f64,
+        processing_time_ms: processing_time.as_millis() as f64,
+        output_time_ms: output_time.as_millis() as f64,
+        total_time_ms: total_time.as_millis() as f64,
+        load_balance_imbalance_ratio: imbalance_ratio,
+        throughput_lines_per_sec,
+    };
+
+    if let Some(stats_json_path) = &args.stats_json {
+        let json = results_to_json(&results, &stats)?;
+        std::fs::write(stats_json_path, &json)
+            .with_context(|| format!("Failed to write stats JSON file: {}", stats_json_path))?;
+        println!("[SYSTEM] Stats + aggregations written to '{}'", stats_json_path);
+    }
+
+    match args.output_format {
+        OutputFormat::Json => {
+            println!("{}", results_to_json(&results, &stats)?);
+        }
+        OutputFormat::Text => {
+            println!("\n🏁 PERFORMANCE SUMMARY 🏁");
+            println!("========================");
+            println!("File mapping:           {:.2}ms", mapping_time.as_millis());
+            println!("Record scan:            {:.2}ms", scan_time.as_millis());
+            println!("Chunk splitting:        {:.2}ms", chunk_time.as_millis());
+            println!("Parallel processing:    {:.2}ms", processing_time.as_millis());
+            println!("Output writing:         {:.2}ms", output_time.as_millis());
+            println!("------------------------");
+            println!("TOTAL EXECUTION TIME:   {:.2}s", total_time.as_secs_f64());
+            println!("Total aggregations written: {}", total_aggregations);
+            println!("========================");
+            println!("🎉 Processing completed successfully! 🎉");
+        }
+    }
+
+    Ok(())
+}
+
+/// Parquet pipeline: skips the mmap/hash-table/partitioning phases entirely and
+/// lets Polars' lazy engine do the scanning, predicate pushdown, and aggregation
+/// in one shot.
+fn run_parquet_pipeline(
+    args: &Args,
+    total_start: Instant,
+    metrics: &[crate::types::MetricSpec],
+) -> Result<()> {
+    if args.frequency {
+        return Err(anyhow::anyhow!(
+            "--frequency is not supported together with --format parquet (there are no raw CSV lines to tally columns over); rerun without one of the two"
+        ));
+    }
+
+    println!("[SYSTEM] Processing mode: Parquet (Polars lazy scan)");
+
+    println!("\n[PHASE 1] Scanning Parquet file...");
+    let scan_start = Instant::now();
+
+    let result = analyze_parquet_file(&args.file_path, metrics)
+        .with_context(|| format!("Failed to analyze parquet file: {}", args.file_path))?;
+
+    let scan_time = scan_start.elapsed();
+    println!("[PHASE 1] ✅ Parquet file scanned and aggregated in {:.2}ms", scan_time.as_millis());
+    println!("[PHASE 1] Total data rows: {}", result.total_lines_processed);
+
+    let total_lines = result.total_lines_processed;
+    let unique_devices = unique_devices_from_results(std::slice::from_ref(&result));
+    let results = vec![result];
+    print_processing_stats(&results);
+
+    println!("[PHASE 2] Writing results to file...");
+    let output_start = Instant::now();
+
+    results_to_parquet(&results, &args.output, metrics)
+        .with_context(|| format!("Failed to write parquet output: {}", args.output))?;
+
+    let output_time = output_start.elapsed();
+    println!("[PHASE 2] ✅ Results written to '{}' in {:.2}ms", args.output, output_time.as_millis());
+
+    let config = ProcessingConfig {
+        delimiter: args.delimiter,
+        ..Default::default()
+    };
+
+    if let Some(export_csv_path) = &args.export_csv {
+        write_aggregations_csv(&results, export_csv_path, &config, metrics)?;
+        println!("[PHASE 2] Deduplicated aggregations exported to '{}'", export_csv_path);
+    }
+
+    if let Some(export_json_path) = &args.export_json {
+        write_aggregations_json(&results, export_json_path, &config, metrics)?;
+        println!("[PHASE 2] Deduplicated aggregations exported to '{}'", export_json_path);
+    }
+
+    let total_aggregations: usize = results.iter().map(|r| r.aggregations.len()).sum();
+    let total_time = total_start.elapsed();
+
+    let throughput_lines_per_sec = if total_time.as_secs_f64() > 0.0 {
+        total_lines as f64 / total_time.as_secs_f64()
+    } else {
+        0.0
+    };
+
+    let stats = ProcessingStats {
+        total_lines,
+        unique_devices,
+        chunks_created: 0,
+        mapping_time_ms: 0.0,
+        hash_building_time_ms: 0.0,
+        partitioning_time_ms: 0.0,
+        processing_time_ms: scan_time.as_millis() as f64,
+        output_time_ms: output_time.as_millis() as f64,
+        total_time_ms: total_time.as_millis() as f64,
+        load_balance_imbalance_ratio: 0.0,
+        throughput_lines_per_sec,
+    };
+
+    if let Some(stats_json_path) = &args.stats_json {
+        let json = results_to_json(&results, &stats)?;
+        std::fs::write(stats_json_path, &json)
+            .with_context(|| format!("Failed to write stats JSON file: {}", stats_json_path))?;
+        println!("[SYSTEM] Stats + aggregations written to '{}'", stats_json_path);
+    }
+
+    match args.output_format {
+        OutputFormat::Json => {
+            println!("{}", results_to_json(&results, &stats)?);
+        }
+        OutputFormat::Text => {
+            println!("\n🏁 PERFORMANCE SUMMARY 🏁");
+            println!("========================");
+            println!("Parquet scan + aggregation: {:.2}ms", scan_time.as_millis());
+            println!("Output writing:             {:.2}ms", output_time.as_millis());
+            println!("------------------------");
+            println!("TOTAL EXECUTION TIME:   {:.2}s", total_time.as_secs_f64());
+            println!("Total aggregations written: {}", total_aggregations);
+            println!("========================");
+            println!("🎉 Processing completed successfully! 🎉");
+        }
+    }
+
     Ok(())
 }
@@ -1,13 +1,21 @@
 use crate::data_analysis::analyze_csv_chunk;
+use crate::resource_monitor::ProgressCounters;
 use crate::types::{AnalysisResults, CsvChunk};
 use anyhow::Result;
 use crossbeam_channel;
 use rayon::prelude::*;
+use std::sync::atomic::Ordering;
 use std::time::Instant;
 use tokio::sync::mpsc;
 
-/// Process multiple CSV chunks in parallel using Rayon
-pub fn process_chunks_parallel(chunks: Vec<CsvChunk>) -> Result<Vec<AnalysisResults>> {
+/// Process multiple CSV chunks in parallel using Rayon. `progress`, when
+/// present, has its line and chunk-completion counters bumped as each chunk
+/// finishes so a `ResourceMonitor` running alongside can report live
+/// throughput and chunks-completed-vs-remaining.
+pub fn process_chunks_parallel(
+    chunks: Vec<CsvChunk>,
+    progress: Option<ProgressCounters>,
+) -> Result<Vec<AnalysisResults>> {
     let start_time = Instant::now();
 
     println!("[PARALLEL] Processing {} chunks in parallel", chunks.len());
@@ -40,6 +48,13 @@ pub fn process_chunks_parallel(chunks: Vec<CsvChunk>) -> Result<Vec<AnalysisResu
                 }
             }
 
+            if let Some(progress) = &progress {
+                progress
+                    .lines
+                    .fetch_add(chunk.line_count as u64, Ordering::Relaxed);
+                progress.chunks_completed.fetch_add(1, Ordering::Relaxed);
+            }
+
             result
         })
         .collect();
@@ -54,7 +69,10 @@ pub fn process_chunks_parallel(chunks: Vec<CsvChunk>) -> Result<Vec<AnalysisResu
 }
 
 /// Async version using Tokio tasks (alternative approach)
-pub async fn process_chunks_async(chunks: Vec<CsvChunk>) -> Result<Vec<AnalysisResults>> {
+pub async fn process_chunks_async(
+    chunks: Vec<CsvChunk>,
+    progress: Option<ProgressCounters>,
+) -> Result<Vec<AnalysisResults>> {
     let start_time = Instant::now();
     let chunk_count = chunks.len();
 
@@ -69,6 +87,7 @@ pub async fn process_chunks_async(chunks: Vec<CsvChunk>) -> Result<Vec<AnalysisR
         .enumerate()
         .map(|(idx, chunk)| {
             let tx = tx.clone();
+            let progress = progress.clone();
             tokio::task::spawn_blocking(move || {
                 println!(
                     "[ASYNC WORKER {}] Processing chunk with {} lines",
@@ -93,6 +112,13 @@ pub async fn process_chunks_async(chunks: Vec<CsvChunk>) -> Result<Vec<AnalysisR
                     }
                 }
 
+                if let Some(progress) = &progress {
+                    progress
+                        .lines
+                        .fetch_add(chunk.line_count as u64, Ordering::Relaxed);
+                    progress.chunks_completed.fetch_add(1, Ordering::Relaxed);
+                }
+
                 // Send result back
                 let _ = tx.send((idx, result));
             })
@@ -140,7 +166,11 @@ impl WorkStealingProcessor {
         Self { workers }
     }
 
-    pub fn process_chunks(self, chunks: Vec<CsvChunk>) -> Result<Vec<AnalysisResults>> {
+    pub fn process_chunks(
+        self,
+        chunks: Vec<CsvChunk>,
+        progress: Option<ProgressCounters>,
+    ) -> Result<Vec<AnalysisResults>> {
         let start_time = Instant::now();
         let chunk_count = chunks.len();
 
@@ -168,6 +198,7 @@ impl WorkStealingProcessor {
         for worker_id in 0..self.workers {
             let work_receiver = work_receiver.clone();
             let result_sender = result_sender.clone();
+            let progress = progress.clone();
 
             let handle = std::thread::spawn(move || {
                 let mut processed_chunks = 0;
@@ -197,6 +228,13 @@ impl WorkStealingProcessor {
                         }
                     }
 
+                    if let Some(progress) = &progress {
+                        progress
+                            .lines
+                            .fetch_add(chunk.line_count as u64, Ordering::Relaxed);
+                        progress.chunks_completed.fetch_add(1, Ordering::Relaxed);
+                    }
+
                     result_sender.send((chunk_idx, result)).unwrap();
                     processed_chunks += 1;
                 }
@@ -0,0 +1,256 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Shared line counter that worker threads bump as they finish a chunk, so
+/// the monitor can derive instantaneous throughput without touching the hot
+/// processing path.
+pub type LineCounter = Arc<AtomicU64>;
+
+pub fn new_line_counter() -> LineCounter {
+    Arc::new(AtomicU64::new(0))
+}
+
+/// Shared chunk-completion counter, bumped once per finished chunk so the
+/// monitor can report progress against the known total chunk count.
+pub type ChunkCounter = Arc<AtomicUsize>;
+
+pub fn new_chunk_counter() -> ChunkCounter {
+    Arc::new(AtomicUsize::new(0))
+}
+
+/// The pair of atomics workers bump as they finish each chunk. Bundled
+/// together so `process_chunks_parallel` and friends only need to thread one
+/// `Option<ProgressCounters>` through instead of two separate counters.
+#[derive(Clone)]
+pub struct ProgressCounters {
+    pub lines: LineCounter,
+    pub chunks_completed: ChunkCounter,
+}
+
+pub fn new_progress_counters() -> ProgressCounters {
+    ProgressCounters {
+        lines: new_line_counter(),
+        chunks_completed: new_chunk_counter(),
+    }
+}
+
+/// One periodic sample of resource usage while processing runs
+#[derive(Debug, Clone, Copy)]
+pub struct ResourceSample {
+    pub elapsed_ms: u128,
+    /// Machine-wide CPU utilization across all cores (from `/proc/stat`), not this process alone
+    pub cpu_percent: Option<f64>,
+    /// This process's resident set size (RSS), not system-wide memory in use
+    pub memory_used_bytes: Option<u64>,
+    pub lines_per_second: f64,
+    pub chunks_completed: usize,
+    pub chunks_total: usize,
+}
+
+/// Background sampling thread that polls `/proc/stat`/`/proc/self/status`
+/// (Linux only) plus the shared progress counters at a fixed interval from
+/// `start()` until `stop()`, printing each sample as it's taken so long jobs
+/// aren't silent until completion.
+pub struct ResourceMonitor {
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<Vec<ResourceSample>>>,
+}
+
+impl ResourceMonitor {
+    /// Start sampling in the background. On non-Linux targets this still
+    /// runs, but every sample's CPU/memory fields are `None` and only the
+    /// line-rate/chunk counters are reported.
+    pub fn start(progress: ProgressCounters, chunks_total: usize, interval: Duration) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_for_thread = Arc::clone(&stop);
+
+        let handle = thread::spawn(move || {
+            let start = Instant::now();
+            let mut samples = Vec::new();
+            let mut last_lines = 0u64;
+            let mut last_cpu_times = read_cpu_times();
+
+            while !stop_for_thread.load(Ordering::Relaxed) {
+                thread::sleep(interval);
+
+                let lines = progress.lines.load(Ordering::Relaxed);
+                let lines_per_second =
+                    lines.saturating_sub(last_lines) as f64 / interval.as_secs_f64();
+                last_lines = lines;
+
+                let current_cpu_times = read_cpu_times();
+                let cpu_percent = match (last_cpu_times, current_cpu_times) {
+                    (Some(prev), Some(curr)) => Some(cpu_percent_from_deltas(prev, curr)),
+                    _ => None,
+                };
+                last_cpu_times = current_cpu_times;
+
+                let chunks_completed = progress.chunks_completed.load(Ordering::Relaxed);
+                let memory_used_bytes = read_memory_used_bytes();
+
+                let sample = ResourceSample {
+                    elapsed_ms: start.elapsed().as_millis(),
+                    cpu_percent,
+                    memory_used_bytes,
+                    lines_per_second,
+                    chunks_completed,
+                    chunks_total,
+                };
+
+                print_live_progress(&sample);
+                samples.push(sample);
+            }
+
+            samples
+        });
+
+        Self {
+            stop,
+            handle: Some(handle),
+        }
+    }
+
+    /// Stop sampling and collect the timeline of samples taken so far.
+    pub fn stop(mut self) -> Vec<ResourceSample> {
+        self.stop.store(true, Ordering::Relaxed);
+        self.handle
+            .take()
+            .and_then(|handle| handle.join().ok())
+            .unwrap_or_default()
+    }
+}
+
+/// Print a single incremental progress line as a sample is taken, so a
+/// long-running job shows signs of life (and a stalled worker or a
+/// pathologically imbalanced partition is visible) instead of going silent
+/// until `stop()`.
+fn print_live_progress(sample: &ResourceSample) {
+    let memory = match sample.memory_used_bytes {
+        Some(bytes) => format!("{:.1} MB", bytes as f64 / 1_048_576.0),
+        None => "n/a".to_string(),
+    };
+
+    println!(
+        "[MONITOR] +{:>6}ms  chunks {}/{}  {:.1} lines/sec  mem {}",
+        sample.elapsed_ms, sample.chunks_completed, sample.chunks_total, sample.lines_per_second, memory
+    );
+}
+
+/// Print peak memory, mean CPU%, and a throughput timeline alongside the
+/// existing phase summary.
+pub fn print_monitor_summary(samples: &[ResourceSample]) {
+    if samples.is_empty() {
+        println!("[MONITOR] No samples collected");
+        return;
+    }
+
+    println!("\n[MONITOR] ====== Resource Usage ======");
+
+    match samples.iter().filter_map(|s| s.memory_used_bytes).max() {
+        Some(peak_memory) => println!(
+            "[MONITOR] Peak memory used: {:.2} MB",
+            peak_memory as f64 / 1_048_576.0
+        ),
+        None => println!("[MONITOR] Memory sampling unavailable on this platform"),
+    }
+
+    let cpu_samples: Vec<f64> = samples.iter().filter_map(|s| s.cpu_percent).collect();
+    if cpu_samples.is_empty() {
+        println!("[MONITOR] CPU sampling unavailable on this platform");
+    } else {
+        let mean_cpu = cpu_samples.iter().sum::<f64>() / cpu_samples.len() as f64;
+        println!("[MONITOR] Mean CPU utilization: {:.1}%", mean_cpu);
+    }
+
+    println!("[MONITOR] Throughput timeline:");
+    for sample in samples {
+        println!(
+            "[MONITOR]   +{:>6}ms  {:>10.1} lines/sec",
+            sample.elapsed_ms, sample.lines_per_second
+        );
+    }
+
+    println!("[MONITOR] =============================\n");
+}
+
+#[cfg(target_os = "linux")]
+#[derive(Debug, Clone, Copy)]
+struct CpuTimes {
+    idle: u64,
+    total: u64,
+}
+
+#[cfg(target_os = "linux")]
+fn read_cpu_times() -> Option<CpuTimes> {
+    let content = std::fs::read_to_string("/proc/stat").ok()?;
+    let line = content.lines().next()?;
+    let values: Vec<u64> = line
+        .split_whitespace()
+        .skip(1) // skip the "cpu" label
+        .filter_map(|field| field.parse().ok())
+        .collect();
+
+    if values.len() < 4 {
+        return None;
+    }
+
+    let idle = values[3] + values.get(4).copied().unwrap_or(0); // idle + iowait
+    let total = values.iter().sum();
+
+    Some(CpuTimes { idle, total })
+}
+
+#[cfg(target_os = "linux")]
+fn cpu_percent_from_deltas(prev: CpuTimes, curr: CpuTimes) -> f64 {
+    let total_delta = curr.total.saturating_sub(prev.total);
+    let idle_delta = curr.idle.saturating_sub(prev.idle);
+
+    if total_delta == 0 {
+        return 0.0;
+    }
+
+    100.0 * total_delta.saturating_sub(idle_delta) as f64 / total_delta as f64
+}
+
+/// This process's own resident set size (RSS), not system-wide memory in
+/// use — on a shared or busy host, `MemTotal - MemAvailable` from
+/// `/proc/meminfo` reflects every process on the machine and has nothing to
+/// do with this job.
+#[cfg(target_os = "linux")]
+fn read_memory_used_bytes() -> Option<u64> {
+    let content = std::fs::read_to_string("/proc/self/status").ok()?;
+
+    for line in content.lines() {
+        if let Some(rest) = line.strip_prefix("VmRSS:") {
+            return parse_meminfo_kb(rest).map(|kb| kb * 1024);
+        }
+    }
+
+    None
+}
+
+#[cfg(target_os = "linux")]
+fn parse_meminfo_kb(field: &str) -> Option<u64> {
+    field.trim().split_whitespace().next()?.parse().ok()
+}
+
+#[cfg(not(target_os = "linux"))]
+#[derive(Debug, Clone, Copy)]
+struct CpuTimes;
+
+#[cfg(not(target_os = "linux"))]
+fn read_cpu_times() -> Option<CpuTimes> {
+    None
+}
+
+#[cfg(not(target_os = "linux"))]
+fn cpu_percent_from_deltas(_prev: CpuTimes, _curr: CpuTimes) -> f64 {
+    0.0
+}
+
+#[cfg(not(target_os = "linux"))]
+fn read_memory_used_bytes() -> Option<u64> {
+    None
+}
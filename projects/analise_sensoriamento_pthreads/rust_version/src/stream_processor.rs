@@ -0,0 +1,477 @@
+use crate::file_mapping::MappedCsvFile;
+use crate::types::{AnalysisResults, MetricSpec, ProcessingConfig, RowFilter, SensorAggregation};
+use ahash::AHashMap;
+use anyhow::{Context, Result};
+use std::time::Instant;
+
+/// Size of each byte window read from the mapped file. Chosen so the
+/// in-flight window plus the carried partial line stay small relative to
+/// typical page cache sizes, regardless of how large the file itself is.
+const WINDOW_SIZE: usize = 8 * 1024 * 1024;
+
+const SENSOR_COLUMNS: &[&str] = &[
+    "temperatura",
+    "umidade",
+    "luminosidade",
+    "ruido",
+    "eco2",
+    "etvoc",
+];
+
+/// Running min/sum/sum-of-squares/max/count for one `(device, year_month,
+/// sensor)` key. `mean`/`std_dev` are only derived at finalization from
+/// these running totals, so folding a new value never needs the full set of
+/// previously-seen values. `samples` is the one exception: `Median`/
+/// `Quantile` have no O(1) running form, so they're only populated (via
+/// `keep_samples`) when one of those metrics was actually requested.
+#[derive(Debug, Clone)]
+struct RunningStats {
+    count: u64,
+    sum: f64,
+    sum_sq: f64,
+    min: f64,
+    max: f64,
+    samples: Option<Vec<f64>>,
+}
+
+impl RunningStats {
+    fn new(value: f64, keep_samples: bool) -> Self {
+        Self {
+            count: 1,
+            sum: value,
+            sum_sq: value * value,
+            min: value,
+            max: value,
+            samples: if keep_samples { Some(vec![value]) } else { None },
+        }
+    }
+
+    fn update(&mut self, value: f64) {
+        self.count += 1;
+        self.sum += value;
+        self.sum_sq += value * value;
+        if value < self.min {
+            self.min = value;
+        }
+        if value > self.max {
+            self.max = value;
+        }
+        if let Some(samples) = &mut self.samples {
+            samples.push(value);
+        }
+    }
+
+    fn mean(&self) -> f64 {
+        if self.count == 0 {
+            0.0
+        } else {
+            self.sum / self.count as f64
+        }
+    }
+
+    /// Sample standard deviation (ddof = 1), matching the non-streaming
+    /// path's `col(sensor).std(1)`; derived from the running sum and
+    /// sum-of-squares rather than the raw samples, so this stays O(1) per
+    /// folded value.
+    fn std_dev(&self) -> f64 {
+        if self.count < 2 {
+            return 0.0;
+        }
+        let n = self.count as f64;
+        let variance = (self.sum_sq - self.sum * self.sum / n) / (n - 1.0);
+        variance.max(0.0).sqrt()
+    }
+
+    fn median(&self) -> f64 {
+        percentile(self.samples.as_deref().unwrap_or(&[]), 50.0)
+    }
+
+    fn quantile(&self, p: u8) -> f64 {
+        percentile(self.samples.as_deref().unwrap_or(&[]), p as f64)
+    }
+
+    /// `(column_name, value)` for one requested metric, in the pipeline's
+    /// standard naming (`valor_maximo`/`valor_medio`/...).
+    fn metric_value(&self, metric: &MetricSpec) -> (String, f64) {
+        let value = match metric {
+            MetricSpec::Max => self.max,
+            MetricSpec::Mean => self.mean(),
+            MetricSpec::Min => self.min,
+            MetricSpec::Std => self.std_dev(),
+            MetricSpec::Median => self.median(),
+            MetricSpec::Quantile(p) => self.quantile(*p),
+        };
+        (metric.column_name(), value)
+    }
+}
+
+/// Linear-interpolated percentile (0-100) over `values`, matching the
+/// `QuantileInterpolation::Linear` semantics `analyze_csv_chunk` uses for
+/// the non-streaming path. `values` need not already be sorted.
+fn percentile(values: &[f64], p: f64) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+    let rank = (p / 100.0) * (sorted.len() - 1) as f64;
+    let lower = rank.floor() as usize;
+    let upper = rank.ceil() as usize;
+    if lower == upper {
+        sorted[lower]
+    } else {
+        let frac = rank - lower as f64;
+        sorted[lower] + (sorted[upper] - sorted[lower]) * frac
+    }
+}
+
+/// Stream a mapped CSV file in fixed-size byte windows, folding running
+/// per-`(device, year_month, sensor)` statistics for each requested
+/// `metrics` entry so peak memory stays proportional to device x month x
+/// sensor cardinality rather than file size — except that `Median`/
+/// `Quantile` inherently need every value seen, so `RunningStats` only
+/// retains raw samples when one of those two was actually requested. A
+/// window boundary can split a record mid-line, so trailing partial bytes
+/// carry into the next window and a line is only parsed once its `\n`
+/// terminator is seen (or the stream ends). The header is parsed exactly
+/// once, from `MappedCsvFile`, before any window is read.
+pub fn process_stream(
+    mapped_file: &MappedCsvFile,
+    config: &ProcessingConfig,
+    metrics: &[MetricSpec],
+) -> Result<AnalysisResults> {
+    let start_time = Instant::now();
+
+    let keep_samples = metrics
+        .iter()
+        .any(|m| matches!(m, MetricSpec::Median | MetricSpec::Quantile(_)));
+
+    let header_columns: Vec<&str> = mapped_file
+        .header
+        .split(config.delimiter)
+        .map(str::trim)
+        .collect();
+
+    let device_idx = header_columns
+        .iter()
+        .position(|&c| c == config.device_column)
+        .ok_or_else(|| anyhow::anyhow!("Device column '{}' not found", config.device_column))?;
+
+    let data_idx = header_columns
+        .iter()
+        .position(|&c| c == "data")
+        .ok_or_else(|| anyhow::anyhow!("'data' column not found in header"))?;
+
+    let sensor_indices: Vec<(usize, &'static str)> = SENSOR_COLUMNS
+        .iter()
+        .filter_map(|&sensor| {
+            header_columns
+                .iter()
+                .position(|&c| c == sensor)
+                .map(|idx| (idx, sensor))
+        })
+        .collect();
+
+    let filter_column_index = match &config.row_filter {
+        Some(filter) => Some(
+            header_columns
+                .iter()
+                .position(|&c| c == filter.column_name())
+                .ok_or_else(|| {
+                    anyhow::anyhow!("Filter column '{}' not found in header", filter.column_name())
+                })?,
+        ),
+        None => None,
+    };
+
+    let mut accumulators: AHashMap<(String, String, &'static str), RunningStats> = AHashMap::new();
+    let mut total_lines = 0usize;
+
+    let data = mapped_file
+        .mmap
+        .get(mapped_file.data_start_offset..)
+        .with_context(|| "Data offset out of bounds for mapped file")?;
+
+    let mut carry: Vec<u8> = Vec::new();
+
+    for window in data.chunks(WINDOW_SIZE) {
+        carry.extend_from_slice(window);
+
+        let mut consumed = 0;
+        while let Some(newline_pos) = carry[consumed..].iter().position(|&b| b == b'\n') {
+            let line_end = consumed + newline_pos;
+            let line = &carry[consumed..line_end];
+            if !line.is_empty() {
+                if let Ok(line_str) = std::str::from_utf8(line) {
+                    if process_line(
+                        line_str,
+                        config.delimiter,
+                        device_idx,
+                        data_idx,
+                        &sensor_indices,
+                        config.row_filter.as_ref(),
+                        filter_column_index,
+                        keep_samples,
+                        &mut accumulators,
+                    ) {
+                        total_lines += 1;
+                    }
+                }
+            }
+            consumed = line_end + 1;
+        }
+
+        carry.drain(0..consumed);
+    }
+
+    // A file without a trailing newline still has a complete final record.
+    if !carry.is_empty() {
+        if let Ok(line_str) = std::str::from_utf8(&carry) {
+            if process_line(
+                line_str,
+                config.delimiter,
+                device_idx,
+                data_idx,
+                &sensor_indices,
+                config.row_filter.as_ref(),
+                filter_column_index,
+                keep_samples,
+                &mut accumulators,
+            ) {
+                total_lines += 1;
+            }
+        }
+    }
+
+    let aggregations = accumulators
+        .into_iter()
+        .map(|((device, year_month, sensor), stats)| SensorAggregation {
+            device,
+            year_month,
+            sensor: sensor.to_string(),
+            values: metrics.iter().map(|metric| stats.metric_value(metric)).collect(),
+        })
+        .collect();
+
+    Ok(AnalysisResults {
+        aggregations,
+        total_lines_processed: total_lines,
+        processing_time_ms: start_time.elapsed().as_millis() as f64,
+    })
+}
+
+/// Process a single line, returning `true` if it was counted (i.e. not
+/// dropped by a missing field or a row filter).
+fn process_line(
+    line: &str,
+    delimiter: char,
+    device_idx: usize,
+    data_idx: usize,
+    sensor_indices: &[(usize, &'static str)],
+    row_filter: Option<&RowFilter>,
+    filter_column_index: Option<usize>,
+    keep_samples: bool,
+    accumulators: &mut AHashMap<(String, String, &'static str), RunningStats>,
+) -> bool {
+    let fields: Vec<&str> = line.split(delimiter).collect();
+
+    if let (Some(filter), Some(filter_idx)) = (row_filter, filter_column_index) {
+        let field_value = fields.get(filter_idx).copied().unwrap_or("");
+        if !filter.matches(field_value) {
+            return false;
+        }
+    }
+
+    let device = match fields.get(device_idx) {
+        Some(&d) if !d.is_empty() => d.to_string(),
+        _ => return false,
+    };
+
+    // The `data` column is a `YYYY-MM-DD HH:MM:SS` timestamp; the year-month
+    // key is just its first 7 characters, no date parsing required.
+    let year_month = match fields.get(data_idx) {
+        Some(date) if date.len() >= 7 => date[..7].to_string(),
+        _ => return false,
+    };
+
+    for &(idx, sensor) in sensor_indices {
+        if let Some(value) = fields.get(idx).and_then(|v| v.parse::<f64>().ok()) {
+            accumulators
+                .entry((device.clone(), year_month.clone(), sensor))
+                .and_modify(|stats| stats.update(value))
+                .or_insert_with(|| RunningStats::new(value, keep_samples));
+        }
+    }
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_process_stream_folds_min_mean_max() -> Result<()> {
+        let mut temp_file = NamedTempFile::new()?;
+        writeln!(temp_file, "id|device|data|temperatura|umidade|luminosidade|ruido|eco2|etvoc")?;
+        writeln!(temp_file, "1|dev1|2024-04-01 10:00:00|20.0|45.0|100|50|400|200")?;
+        writeln!(temp_file, "2|dev1|2024-04-01 11:00:00|30.0|46.0|110|55|410|210")?;
+        writeln!(temp_file, "3|dev2|2024-05-01 12:00:00|22.5|44.0|95|48|395|195")?;
+
+        let mapped_file = MappedCsvFile::new(temp_file.path())?;
+        let config = ProcessingConfig {
+            delimiter: '|',
+            ..Default::default()
+        };
+
+        let results = process_stream(&mapped_file, &config, &crate::types::default_metrics())?;
+        assert_eq!(results.total_lines_processed, 3);
+
+        let dev1_temp = results
+            .aggregations
+            .iter()
+            .find(|a| a.device == "dev1" && a.sensor == "temperatura" && a.year_month == "2024-04")
+            .expect("dev1 temperatura aggregation should exist");
+
+        let max = dev1_temp
+            .values
+            .iter()
+            .find(|(name, _)| name == "valor_maximo")
+            .unwrap()
+            .1;
+        let min = dev1_temp
+            .values
+            .iter()
+            .find(|(name, _)| name == "valor_minimo")
+            .unwrap()
+            .1;
+        let mean = dev1_temp
+            .values
+            .iter()
+            .find(|(name, _)| name == "valor_medio")
+            .unwrap()
+            .1;
+
+        assert_eq!(max, 30.0);
+        assert_eq!(min, 20.0);
+        assert_eq!(mean, 25.0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_stream_handles_window_split_lines() -> Result<()> {
+        let mut temp_file = NamedTempFile::new()?;
+        writeln!(temp_file, "id|device|data|temperatura|umidade|luminosidade|ruido|eco2|etvoc")?;
+        for i in 0..50 {
+            writeln!(
+                temp_file,
+                "{}|dev1|2024-04-01 10:00:00|{}.0|45.0|100|50|400|200",
+                i, i
+            )?;
+        }
+
+        let mapped_file = MappedCsvFile::new(temp_file.path())?;
+        let config = ProcessingConfig {
+            delimiter: '|',
+            ..Default::default()
+        };
+
+        // Force a window size far smaller than a single line so windows
+        // repeatedly split records mid-byte.
+        let data = mapped_file
+            .mmap
+            .get(mapped_file.data_start_offset..)
+            .unwrap();
+        let mut accumulators: AHashMap<(String, String, &'static str), RunningStats> =
+            AHashMap::new();
+        let header_columns: Vec<&str> = mapped_file.header.split(config.delimiter).collect();
+        let device_idx = header_columns.iter().position(|&c| c == "device").unwrap();
+        let data_idx = header_columns.iter().position(|&c| c == "data").unwrap();
+        let sensor_indices: Vec<(usize, &'static str)> = SENSOR_COLUMNS
+            .iter()
+            .filter_map(|&sensor| {
+                header_columns
+                    .iter()
+                    .position(|&c| c == sensor)
+                    .map(|idx| (idx, sensor))
+            })
+            .collect();
+
+        let mut carry: Vec<u8> = Vec::new();
+        for window in data.chunks(5) {
+            carry.extend_from_slice(window);
+            let mut consumed = 0;
+            while let Some(newline_pos) = carry[consumed..].iter().position(|&b| b == b'\n') {
+                let line_end = consumed + newline_pos;
+                let line = &carry[consumed..line_end];
+                if !line.is_empty() {
+                    if let Ok(line_str) = std::str::from_utf8(line) {
+                        process_line(
+                            line_str,
+                            config.delimiter,
+                            device_idx,
+                            data_idx,
+                            &sensor_indices,
+                            config.row_filter.as_ref(),
+                            None,
+                            false,
+                            &mut accumulators,
+                        );
+                    }
+                }
+                consumed = line_end + 1;
+            }
+            carry.drain(0..consumed);
+        }
+
+        let key = ("dev1".to_string(), "2024-04".to_string(), "temperatura");
+        assert_eq!(accumulators.get(&key).unwrap().count, 50);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_stream_honors_requested_metrics() -> Result<()> {
+        let mut temp_file = NamedTempFile::new()?;
+        writeln!(temp_file, "id|device|data|temperatura|umidade|luminosidade|ruido|eco2|etvoc")?;
+        writeln!(temp_file, "1|dev1|2024-04-01 10:00:00|10.0|45.0|100|50|400|200")?;
+        writeln!(temp_file, "2|dev1|2024-04-01 11:00:00|20.0|46.0|110|55|410|210")?;
+        writeln!(temp_file, "3|dev1|2024-04-01 12:00:00|30.0|44.0|95|48|395|195")?;
+
+        let mapped_file = MappedCsvFile::new(temp_file.path())?;
+        let config = ProcessingConfig {
+            delimiter: '|',
+            ..Default::default()
+        };
+        let metrics = vec![MetricSpec::Std, MetricSpec::Median];
+
+        let results = process_stream(&mapped_file, &config, &metrics)?;
+        let dev1_temp = results
+            .aggregations
+            .iter()
+            .find(|a| a.device == "dev1" && a.sensor == "temperatura")
+            .expect("dev1 temperatura aggregation should exist");
+
+        // Only the requested metrics should appear, none of the
+        // max/mean/min default columns.
+        assert_eq!(dev1_temp.values.len(), 2);
+        assert!(dev1_temp
+            .values
+            .iter()
+            .any(|(name, _)| name == "valor_desvio_padrao"));
+        let median = dev1_temp
+            .values
+            .iter()
+            .find(|(name, _)| name == "valor_mediana")
+            .unwrap()
+            .1;
+        assert_eq!(median, 20.0);
+
+        Ok(())
+    }
+}
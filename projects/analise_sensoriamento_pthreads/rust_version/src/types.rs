@@ -1,6 +1,91 @@
 use ahash::AHashMap;
+use clap::ValueEnum;
 use std::sync::Arc;
 
+/// Input/output format for the analysis pipeline
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum InputFormat {
+    /// `|`-delimited CSV, parsed with the hand-rolled mapping/hash-table pipeline
+    Csv,
+    /// Columnar Parquet, read and written directly via Polars
+    Parquet,
+}
+
+impl Default for InputFormat {
+    fn default() -> Self {
+        InputFormat::Csv
+    }
+}
+
+/// How the aggregation results and run statistics are reported to stdout
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Decorated, human-readable performance summary (the historical default)
+    Text,
+    /// A single JSON document with the aggregations and timing breakdown, for scripts/CI;
+    /// the phase progress lines are still printed to stdout before it
+    Json,
+}
+
+impl Default for OutputFormat {
+    fn default() -> Self {
+        OutputFormat::Text
+    }
+}
+
+/// A single requested aggregation metric for a sensor column
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MetricSpec {
+    Max,
+    Mean,
+    Min,
+    Std,
+    Median,
+    /// A `pNN` quantile, e.g. `p95` -> `Quantile(95)`
+    Quantile(u8),
+}
+
+impl MetricSpec {
+    /// Output column name for this metric (mirrors the existing
+    /// `valor_maximo`/`valor_medio`/`valor_minimo` naming)
+    pub fn column_name(&self) -> String {
+        match self {
+            MetricSpec::Max => "valor_maximo".to_string(),
+            MetricSpec::Mean => "valor_medio".to_string(),
+            MetricSpec::Min => "valor_minimo".to_string(),
+            MetricSpec::Std => "valor_desvio_padrao".to_string(),
+            MetricSpec::Median => "valor_mediana".to_string(),
+            MetricSpec::Quantile(p) => format!("valor_p{}", p),
+        }
+    }
+}
+
+/// The metrics computed when none are explicitly requested, matching the
+/// pipeline's historical max/mean/min behavior.
+pub fn default_metrics() -> Vec<MetricSpec> {
+    vec![MetricSpec::Max, MetricSpec::Mean, MetricSpec::Min]
+}
+
+/// Parse a comma-separated `--metrics` spec such as `max,mean,min,std,median,p95`
+pub fn parse_metrics(spec: &str) -> Result<Vec<MetricSpec>, String> {
+    spec.split(',')
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .map(|s| match s {
+            "max" => Ok(MetricSpec::Max),
+            "mean" => Ok(MetricSpec::Mean),
+            "min" => Ok(MetricSpec::Min),
+            "std" => Ok(MetricSpec::Std),
+            "median" => Ok(MetricSpec::Median),
+            other if other.starts_with('p') => other[1..]
+                .parse::<u8>()
+                .map(MetricSpec::Quantile)
+                .map_err(|_| format!("Invalid quantile metric '{}', expected e.g. 'p95'", other)),
+            other => Err(format!("Unknown metric '{}'", other)),
+        })
+        .collect()
+}
+
 /// Represents a slice of CSV data with associated metadata
 #[derive(Debug, Clone)]
 pub struct CsvChunk {
@@ -12,6 +97,8 @@ pub struct CsvChunk {
     pub device_ids: Vec<String>,
     /// Number of data lines (excluding header)
     pub line_count: usize,
+    /// Aggregation metrics to compute for each sensor column
+    pub metrics: Vec<MetricSpec>,
 }
 
 /// Entry for a specific device containing all its data lines
@@ -26,15 +113,15 @@ pub struct DeviceEntry {
 /// Thread-safe hash table mapping device IDs to their data lines
 pub type DeviceHashTable<'a> = AHashMap<String, Vec<&'a str>>;
 
-/// Represents sensor data aggregations
+/// Represents sensor data aggregations. `values` holds one `(column_name,
+/// value)` pair per requested `MetricSpec`, in the order the metrics were
+/// requested, so output columns can be generated dynamically.
 #[derive(Debug, Clone)]
 pub struct SensorAggregation {
     pub device: String,
     pub year_month: String,
     pub sensor: String,
-    pub max_value: f64,
-    pub mean_value: f64,
-    pub min_value: f64,
+    pub values: Vec<(String, f64)>,
 }
 
 /// Processed results from data analysis
@@ -45,6 +132,119 @@ pub struct AnalysisResults {
     pub processing_time_ms: f64,
 }
 
+/// A numeric comparison used by `RowFilter::NumericRange`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ComparisonOp {
+    GreaterThan,
+    GreaterThanOrEqual,
+    LessThan,
+    LessThanOrEqual,
+    Equal,
+}
+
+impl ComparisonOp {
+    fn matches(&self, value: f64, threshold: f64) -> bool {
+        match self {
+            ComparisonOp::GreaterThan => value > threshold,
+            ComparisonOp::GreaterThanOrEqual => value >= threshold,
+            ComparisonOp::LessThan => value < threshold,
+            ComparisonOp::LessThanOrEqual => value <= threshold,
+            ComparisonOp::Equal => (value - threshold).abs() < f64::EPSILON,
+        }
+    }
+}
+
+/// A row-level predicate evaluated against one named column before a line is
+/// bucketed by device. `Contains` is a cheap substring test; `Regex` is only
+/// compiled when the user opts into it, so the hot parsing loop isn't paying
+/// for regex when a plain substring match would do.
+#[derive(Debug, Clone)]
+pub enum RowFilter {
+    Contains {
+        column: String,
+        needle: String,
+    },
+    Regex {
+        column: String,
+        pattern: regex::Regex,
+    },
+    NumericRange {
+        column: String,
+        op: ComparisonOp,
+        threshold: f64,
+    },
+}
+
+impl RowFilter {
+    pub fn column_name(&self) -> &str {
+        match self {
+            RowFilter::Contains { column, .. } => column,
+            RowFilter::Regex { column, .. } => column,
+            RowFilter::NumericRange { column, .. } => column,
+        }
+    }
+
+    /// Evaluate the predicate against the raw field value for this filter's column.
+    pub fn matches(&self, field_value: &str) -> bool {
+        match self {
+            RowFilter::Contains { needle, .. } => field_value.contains(needle.as_str()),
+            RowFilter::Regex { pattern, .. } => pattern.is_match(field_value),
+            RowFilter::NumericRange { op, threshold, .. } => field_value
+                .parse::<f64>()
+                .map(|value| op.matches(value, *threshold))
+                .unwrap_or(false),
+        }
+    }
+}
+
+/// Parse a `--filter` spec such as `device=~^sensor_[0-9]+$`, `status~ERROR`,
+/// or `temperatura>30` into a `RowFilter`. Checked in order: regex (`=~`),
+/// then numeric comparisons (`>=`, `<=`, `>`, `<`, `==`), then plain
+/// substring (`~`).
+pub fn parse_row_filter(spec: &str) -> Result<RowFilter, String> {
+    if let Some((column, pattern)) = spec.split_once("=~") {
+        let pattern = regex::Regex::new(pattern)
+            .map_err(|e| format!("Invalid regex '{}': {}", pattern, e))?;
+        return Ok(RowFilter::Regex {
+            column: column.trim().to_string(),
+            pattern,
+        });
+    }
+
+    let numeric_ops = [
+        (">=", ComparisonOp::GreaterThanOrEqual),
+        ("<=", ComparisonOp::LessThanOrEqual),
+        ("==", ComparisonOp::Equal),
+        (">", ComparisonOp::GreaterThan),
+        ("<", ComparisonOp::LessThan),
+    ];
+    for (op_str, op) in numeric_ops {
+        if let Some((column, value)) = spec.split_once(op_str) {
+            let threshold = value
+                .trim()
+                .parse::<f64>()
+                .map_err(|_| format!("Invalid numeric threshold '{}' in filter '{}'", value, spec))?;
+            return Ok(RowFilter::NumericRange {
+                column: column.trim().to_string(),
+                op,
+                threshold,
+            });
+        }
+    }
+
+    if let Some((column, needle)) = spec.split_once('~') {
+        return Ok(RowFilter::Contains {
+            column: column.trim().to_string(),
+            needle: needle.to_string(),
+        });
+    }
+
+    Err(format!(
+        "Unrecognized filter spec '{}', expected e.g. 'device~sensor', 'temperatura>30', or 'device=~^dev[0-9]+$'",
+        spec
+    ))
+}
+
 /// Configuration for CSV processing
 #[derive(Debug, Clone)]
 pub struct ProcessingConfig {
@@ -56,6 +256,13 @@ pub struct ProcessingConfig {
     pub num_workers: usize,
     /// CSV delimiter (default: "|")
     pub delimiter: char,
+    /// Optional row-level predicate; lines failing it are skipped before
+    /// being bucketed by device
+    pub row_filter: Option<RowFilter>,
+    /// Whether the live resource/throughput monitor runs alongside chunk processing
+    pub monitor_enabled: bool,
+    /// Monitor sampling interval in milliseconds
+    pub monitor_interval_ms: u64,
 }
 
 impl Default for ProcessingConfig {
@@ -65,12 +272,15 @@ impl Default for ProcessingConfig {
             device_column: "device".to_string(),
             num_workers: num_cpus::get(),
             delimiter: '|',
+            row_filter: None,
+            monitor_enabled: false,
+            monitor_interval_ms: 500,
         }
     }
 }
 
 /// Statistics about the CSV file and processing
-#[derive(Debug)]
+#[derive(Debug, serde::Serialize)]
 pub struct ProcessingStats {
     pub total_lines: usize,
     pub unique_devices: usize,
@@ -79,5 +289,8 @@ pub struct ProcessingStats {
     pub hash_building_time_ms: f64,
     pub partitioning_time_ms: f64,
     pub processing_time_ms: f64,
+    pub output_time_ms: f64,
     pub total_time_ms: f64,
+    pub load_balance_imbalance_ratio: f64,
+    pub throughput_lines_per_sec: f64,
 }
@@ -1,61 +1,76 @@
-// Importação de bibliotecas necessárias:
-// Arc (Atomic Reference Counter) permite compartilhar dados entre threads de forma segura
-// Mutex (Mutual Exclusion) garante acesso exclusivo aos dados compartilhados
-use std::sync::{Arc, Mutex};
 // Biblioteca para criação e gerenciamento de threads
 use std::thread;
 
 // Constante que define o número de incrementos que cada thread realizará
-const NUM_INCREMENTOS: i32 = 10000000;
+const NUM_INCREMENTOS: i64 = 10000000;
 
-// Função que incrementa um contador protegido por mutex
-// Recebe uma referência ao contador compartilhado entre as threads
-fn incrementa(contador: &Arc<Mutex<i32>>) {
-    // Loop que executa o número definido de incrementos
+// Executa `work` uma vez por shard (tipicamente uma por thread), deixando
+// cada uma acumular seu próprio valor de `T` sem tocar em nenhum estado
+// compartilhado, e só combina os resultados em um único total depois que
+// todas as threads terminam (`thread::scope` garante isso antes de
+// retornar). Isso substitui um `Mutex` serializando cada atualização por N
+// acumuladores independentes e sem contenção, reduzidos uma única vez no
+// final — o mesmo padrão serve tanto para o contador abaixo quanto para
+// somar uma coluna numérica do CSV particionada em chunks.
+fn sharded_reduce<T, F>(num_shards: usize, work: F) -> T
+where
+    T: Default + std::ops::Add<Output = T> + Send,
+    F: Fn(usize) -> T + Sync,
+{
+    thread::scope(|scope| {
+        let handles: Vec<_> = (0..num_shards)
+            .map(|shard_id| scope.spawn(|| work(shard_id)))
+            .collect();
+
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .fold(T::default(), |total, shard_value| total + shard_value)
+    })
+}
+
+// Shard do contador: cada thread possui sua própria variável local
+// (nenhum lock, nenhum atomic) e a devolve como resultado ao final do loop.
+fn incrementa_shard(_shard_id: usize) -> i64 {
+    let mut contador_local = 0i64;
     for _ in 0..NUM_INCREMENTOS {
-        // Obtém o bloqueio do mutex, garantindo acesso exclusivo ao contador
-        // unwrap() é usado para tratar o Result retornado por lock()
-        let mut valor = contador.lock().unwrap();
-        // Incrementa o valor do contador em 1
-        *valor += 1;
+        contador_local += 1;
     }
+    contador_local
+}
+
+// Demonstra o mesmo padrão aplicado a somar uma coluna numérica de CSV já
+// particionada em chunks (como os chunks produzidos por `partition_by_device`
+// no pipeline de análise de sensoriamento), em vez de um total compartilhado
+// protegido por mutex.
+fn soma_coluna_csv(chunks: &[Vec<f64>]) -> f64 {
+    sharded_reduce(chunks.len(), |shard_id| chunks[shard_id].iter().sum())
 }
 
 fn main() {
-    // Cria um contador protegido por mutex e o envolve em Arc para permitir propriedade compartilhada
-    // O contador é inicializado com valor 0
-    let contador = Arc::new(Mutex::new(0));
-    // Vetor para armazenar os handles das threads
-    let mut handles = vec![];
     // Determina o número de threads baseado no número de núcleos disponíveis no sistema
     let num_threads: usize = std::thread::available_parallelism().unwrap().get();
 
-    // Criação das threads
-    for _ in 0..num_threads {
-        // Clona o Arc para que cada thread tenha sua própria referência ao contador
-        let contador_clone = Arc::clone(&contador);
-        // Cria uma nova thread e move o contador clonado para dentro dela
-        let handle = thread::spawn(move || {
-            // Chama a função incrementa passando a referência ao contador
-            incrementa(&contador_clone);
-        });
-        // Armazena o handle da thread no vetor para poder aguardar seu término posteriormente
-        handles.push(handle);
-    }
-
-    // Aguarda todas as threads terminarem
-    for handle in handles {
-        // join() bloqueia a thread principal até que a thread correspondente termine
-        // unwrap() é usado para tratar o Result retornado por join()
-        handle.join().unwrap();
-    }
+    // Cada thread acumula seu próprio shard lock-free; a redução final
+    // soma todos os shards de uma vez só, após o join.
+    let contador_final = sharded_reduce(num_threads, incrementa_shard);
 
     // Exibe os resultados
     // Calcula o valor esperado do contador (número de threads * número de incrementos)
     println!(
         "Valor final esperado do contador: {}",
-        num_threads as i32 * NUM_INCREMENTOS
+        num_threads as i64 * NUM_INCREMENTOS
+    );
+    println!("Valor final do contador: {}", contador_final);
+
+    // Exemplo da mesma redução aplicada a colunas numéricas de CSV
+    let chunks_exemplo = vec![
+        vec![1.0, 2.0, 3.0],
+        vec![4.0, 5.0],
+        vec![6.0, 7.0, 8.0, 9.0],
+    ];
+    println!(
+        "Soma da coluna CSV de exemplo: {}",
+        soma_coluna_csv(&chunks_exemplo)
     );
-    // Obtém o valor final do contador e o exibe
-    println!("Valor final do contador: {}", *contador.lock().unwrap());
 }